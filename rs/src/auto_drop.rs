@@ -0,0 +1,324 @@
+use crate::coins::{
+	drop_coins, ActiveChute, AutoDrop, AutoDropChutes, AutoDropTimer, Coin, CoinDropReason,
+	CoinQueue, DropCoin, DropRng,
+};
+use crate::jackpot::JackpotWon;
+use crate::machine::{CoinCollected, DropZone, MachineConfig, Piston};
+use crate::{cents, GameState};
+use bevy::prelude::*;
+use currency::Currency;
+use rand::Rng;
+use std::time::Duration;
+
+pub struct AutoDropPlugin;
+
+impl Plugin for AutoDropPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<AutoDropProgram>()
+			.init_resource::<AutoDropRun>()
+			.add_systems(
+				FixedUpdate,
+				(
+					start_run,
+					track_drops,
+					track_collections,
+					auto_drop_coins.run_if(AutoDrop::is_enabled),
+				)
+					.chain()
+					.after(drop_coins)
+					.run_if(in_state(GameState::Playing)),
+			);
+	}
+}
+
+/// How auto-drop decides when and where to drop, and when to give up.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct AutoDropProgram {
+	pub name: String,
+	pub pattern: DropPattern,
+	pub aim: AimPattern,
+	pub stop: StopConditions,
+}
+
+impl AutoDropProgram {
+	/// Programs the player can cycle through. They all share the same stop conditions.
+	pub fn presets(stop: &StopConditions) -> Vec<Self> {
+		vec![
+			Self {
+				name: "Steady".into(),
+				pattern: DropPattern::Interval,
+				aim: AimPattern::Random,
+				stop: stop.clone(),
+			},
+			Self {
+				name: "Burst x5".into(),
+				pattern: DropPattern::Burst { count: 5 },
+				aim: AimPattern::Random,
+				stop: stop.clone(),
+			},
+			Self {
+				name: "Random 0.5-3s".into(),
+				pattern: DropPattern::Random {
+					min: Duration::from_millis(500),
+					max: Duration::from_secs(3),
+				},
+				aim: AimPattern::Random,
+				stop: stop.clone(),
+			},
+			Self {
+				name: "Piston sync".into(),
+				pattern: DropPattern::PistonPhase { phase: 0.0 },
+				aim: AimPattern::Random,
+				stop: stop.clone(),
+			},
+			Self {
+				name: "Sweep".into(),
+				pattern: DropPattern::Interval,
+				aim: AimPattern::Sweep { step: 0.25 },
+				stop: stop.clone(),
+			},
+		]
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum DropPattern {
+	/// One coin each time the [`AutoDropTimer`] finishes.
+	#[default]
+	Interval,
+	/// `count` coins at once each time the [`AutoDropTimer`] finishes.
+	Burst { count: usize },
+	/// One coin at a time, waiting a random time between `min` and `max` between them.
+	/// Overrides the [`AutoDropTimer`] duration.
+	Random { min: Duration, max: Duration },
+	/// One coin each time the piston passes `phase` (see [`Piston::phase`]).
+	PistonPhase { phase: f32 },
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum AimPattern {
+	/// Anywhere across the chute.
+	#[default]
+	Random,
+	/// Back and forth across the chute, moving `step` (in [`DropCoin::aim`] units) each coin.
+	Sweep { step: f32 },
+}
+
+/// Turns auto-drop off once any of these is reached. Amounts are for the current run,
+/// i.e. since auto-drop was last turned on.
+#[derive(Debug, Clone, Default)]
+pub struct StopConditions {
+	pub max_coins: Option<u64>,
+	pub max_spend: Option<Currency>,
+	/// Stop once this much has been lost (spent minus collected).
+	pub stop_loss: Option<Currency>,
+	/// Stop once this much has been won (collected minus spent).
+	pub take_profit: Option<Currency>,
+	/// Stop once the jackpot has been won.
+	pub after_jackpot: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopReason {
+	MaxCoins,
+	MaxSpend,
+	StopLoss,
+	TakeProfit,
+	Jackpot,
+}
+
+impl StopConditions {
+	pub fn check(&self, run: &AutoDropRun) -> Option<StopReason> {
+		let net = run.collected_cents - run.spent_cents;
+		if self.after_jackpot && run.jackpot_won {
+			Some(StopReason::Jackpot)
+		} else if self.max_coins.is_some_and(|max| run.coins >= max) {
+			Some(StopReason::MaxCoins)
+		} else if self
+			.max_spend
+			.as_ref()
+			.is_some_and(|max| run.spent_cents >= cents(max))
+		{
+			Some(StopReason::MaxSpend)
+		} else if self
+			.stop_loss
+			.as_ref()
+			.is_some_and(|loss| -net >= cents(loss))
+		{
+			Some(StopReason::StopLoss)
+		} else if self
+			.take_profit
+			.as_ref()
+			.is_some_and(|profit| net >= cents(profit))
+		{
+			Some(StopReason::TakeProfit)
+		} else {
+			None
+		}
+	}
+}
+
+/// Progress of the current auto-drop run.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct AutoDropRun {
+	/// Coins that have actually dropped, not counting ones still queued, which can be
+	/// cancelled or purged when the run stops.
+	pub coins: u64,
+	/// What those coins were worth.
+	pub spent_cents: i64,
+	pub collected_cents: i64,
+	/// Current position of [`AimPattern::Sweep`].
+	pub aim: f32,
+	pub sweep_dir: f32,
+	pub last_phase: Option<f32>,
+	pub jackpot_won: bool,
+	/// Why the last run ended, if it ended itself.
+	pub stopped: Option<StopReason>,
+}
+
+pub fn start_run(auto: Res<AutoDrop>, mut run: ResMut<AutoDropRun>) {
+	if auto.is_changed() && **auto {
+		*run = AutoDropRun {
+			aim: -1.0,
+			sweep_dir: 1.0,
+			..default()
+		};
+	}
+}
+
+pub fn track_drops(
+	auto: Res<AutoDrop>,
+	mut run: ResMut<AutoDropRun>,
+	dropped: Query<(&Coin, &CoinDropReason), Added<Coin>>,
+) {
+	if !**auto {
+		return;
+	}
+	for (coin, reason) in &dropped {
+		if *reason == CoinDropReason::Auto {
+			run.coins += 1;
+			run.spent_cents += cents(&coin.value);
+		}
+	}
+}
+
+pub fn track_collections(
+	auto: Res<AutoDrop>,
+	mut run: ResMut<AutoDropRun>,
+	mut events: EventReader<CoinCollected>,
+	mut jackpots: EventReader<JackpotWon>,
+) {
+	for ev in events.read() {
+		if **auto {
+			run.collected_cents += cents(&ev.paid);
+		}
+	}
+	for ev in jackpots.read() {
+		if **auto {
+			run.collected_cents += cents(&ev.amount);
+			run.jackpot_won = true;
+		}
+	}
+}
+
+pub fn auto_drop_coins(
+	mut events: EventWriter<DropCoin>,
+	mut timer: ResMut<AutoDropTimer>,
+	t: Res<Time>,
+	mut auto: ResMut<AutoDrop>,
+	program: Res<AutoDropProgram>,
+	mut run: ResMut<AutoDropRun>,
+	chutes: Res<AutoDropChutes>,
+	active_chute: Res<ActiveChute>,
+	drop_zones: Query<(&DropZone, &CoinQueue)>,
+	pistons: Query<&Piston>,
+	config: Res<MachineConfig>,
+	mut rng: ResMut<DropRng>,
+	mut next_chute: Local<usize>,
+) {
+	if let Some(reason) = program.stop.check(&run) {
+		info!(?reason, coins = run.coins, "Auto-drop stopped");
+		run.stopped = Some(reason);
+		**auto = false;
+		return;
+	}
+
+	timer.tick(t.delta());
+	// Chutes that still have an auto-drop coin waiting, which gets to drop first
+	let busy = |chute: usize| {
+		drop_zones.iter().any(|(zone, queue)| {
+			zone.0 == chute && queue.iter().any(|ev| ev.reason == CoinDropReason::Auto)
+		})
+	};
+
+	let count = match program.pattern {
+		DropPattern::Interval => usize::from(timer.finished()),
+		DropPattern::Burst { count } => {
+			if timer.finished() {
+				count
+			} else {
+				0
+			}
+		}
+		DropPattern::Random { min, max } => {
+			if timer.finished() {
+				let secs = rng.0.gen_range(min.as_secs_f32()..=max.as_secs_f32());
+				timer.set_duration(Duration::from_secs_f32(secs));
+				1
+			} else {
+				0
+			}
+		}
+		DropPattern::PistonPhase { phase } => {
+			let Some(piston) = pistons.iter().next() else {
+				return;
+			};
+			let curr = piston.phase();
+			let crossed = run.last_phase.is_some_and(|last| {
+				if last <= curr {
+					last < phase && phase <= curr
+				} else {
+					// Wrapped around to the next cycle
+					phase > last || phase <= curr
+				}
+			});
+			run.last_phase = Some(curr);
+			usize::from(crossed)
+		}
+	};
+	let count = match program.stop.max_coins {
+		Some(max) => count.min(max.saturating_sub(run.coins) as usize),
+		None => count,
+	};
+
+	for _ in 0..count {
+		let mut ev = DropCoin::new(config.coin_value.clone(), CoinDropReason::Auto);
+		match *chutes {
+			AutoDropChutes::Active => {
+				if busy(**active_chute) {
+					break;
+				}
+			}
+			AutoDropChutes::Cycle => {
+				let chute_count = drop_zones.iter().count().max(1);
+				let Some(chute) = (0..chute_count)
+					.map(|i| (*next_chute + i) % chute_count)
+					.find(|&chute| !busy(chute))
+				else {
+					break;
+				};
+				*next_chute = chute + 1;
+				ev = ev.in_chute(chute);
+			}
+		}
+		if let AimPattern::Sweep { step } = program.aim {
+			ev = ev.aimed(run.aim);
+			let next = run.aim + step * run.sweep_dir;
+			if next.abs() > 1.0 {
+				run.sweep_dir = -run.sweep_dir;
+			}
+			run.aim = next.clamp(-1.0, 1.0);
+		}
+		events.send(ev);
+	}
+}
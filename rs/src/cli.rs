@@ -0,0 +1,247 @@
+use crate::auto_drop::StopConditions;
+use crate::pegs::{PegLayout, PegPattern, PEG_FIELD};
+use crate::pusher::JamRecovery;
+use crate::strategy::STRATEGIES;
+use crate::sweep::SweepRange;
+use currency::Currency;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Command-line options, parsed before the app is built.
+#[derive(Debug, Clone)]
+pub struct Options {
+	/// Simulate without a window as fast as possible, then print a report.
+	pub headless: bool,
+	/// Simulated time for headless runs.
+	pub duration: Duration,
+	/// Name of a [`crate::strategy::Strategy`] to play with.
+	pub strategy: Option<String>,
+	/// Parameter ranges to simulate every combination of, headless.
+	pub sweep: Vec<SweepRange>,
+	/// Where to write sweep results, instead of stdout.
+	pub csv: Option<PathBuf>,
+	/// Where to write the exit lane histogram after a headless run.
+	pub lanes_csv: Option<PathBuf>,
+	/// Enables the [`crate::house_edge::HouseEdge`] controller with this target hold.
+	pub target_hold: Option<f32>,
+	/// Where to append house edge adjustments.
+	pub house_edge_audit: Option<PathBuf>,
+	/// Drive the pusher with a motor that stalls above this force.
+	pub pusher_max_force: Option<f32>,
+	/// Seconds the force-limited pusher can be held back before it counts as jammed.
+	pub pusher_jam_after: f32,
+	/// What the force-limited pusher does once it jams.
+	pub pusher_recovery: JamRecovery,
+	/// Coins to pre-fill the bed with before play starts.
+	pub prefill: usize,
+	/// Coins to pre-fill the shelf behind the piston with.
+	pub prefill_shelf: usize,
+	/// Peg layout to use instead of the machine's own.
+	pub pegs: Option<PegPattern>,
+	/// Where to save the peg layout that gets used.
+	pub export_pegs: Option<PathBuf>,
+	/// Where to keep the jackpot pool between sessions.
+	pub jackpot_file: Option<PathBuf>,
+	/// Where to keep permanent upgrades between sessions.
+	pub upgrades_file: Option<PathBuf>,
+	/// Where to keep the balance and auto-drop settings between sessions.
+	pub session_file: Option<PathBuf>,
+	/// Where to record player inputs to.
+	pub record: Option<PathBuf>,
+	/// Recording to play back instead of recording.
+	pub replay: Option<PathBuf>,
+	/// Seed for anything random that should be repeatable.
+	pub seed: Option<u64>,
+	pub stop: StopConditions,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			headless: false,
+			duration: Duration::from_secs(600),
+			strategy: None,
+			sweep: Vec::new(),
+			csv: None,
+			lanes_csv: None,
+			target_hold: None,
+			house_edge_audit: None,
+			pusher_max_force: None,
+			pusher_jam_after: 2.0,
+			pusher_recovery: JamRecovery::default(),
+			prefill: 0,
+			prefill_shelf: 0,
+			pegs: None,
+			export_pegs: None,
+			jackpot_file: None,
+			upgrades_file: None,
+			session_file: None,
+			record: None,
+			replay: None,
+			seed: None,
+			stop: StopConditions::default(),
+		}
+	}
+}
+
+impl Options {
+	pub fn from_args() -> Result<Self, String> {
+		Self::parse(std::env::args().skip(1))
+	}
+
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+		let mut this = Self::default();
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			match &*arg {
+				"--headless" => this.headless = true,
+				"--duration" => this.duration = Duration::from_secs_f32(value(&arg, args.next())?),
+				"--strategy" => {
+					let name: String = value(&arg, args.next())?;
+					if !STRATEGIES.contains(&&*name) {
+						return Err(format!(
+							"Unknown strategy `{name}`, expected one of {STRATEGIES:?}"
+						));
+					}
+					this.strategy = Some(name);
+				}
+				"--sweep" => this.sweep.push(value(&arg, args.next())?),
+				"--csv" => this.csv = Some(value(&arg, args.next())?),
+				"--lanes-csv" => this.lanes_csv = Some(value(&arg, args.next())?),
+				"--target-hold" => this.target_hold = Some(value(&arg, args.next())?),
+				"--house-edge-audit" => this.house_edge_audit = Some(value(&arg, args.next())?),
+				"--pusher-max-force" => this.pusher_max_force = Some(value(&arg, args.next())?),
+				"--pusher-jam-after" => this.pusher_jam_after = value(&arg, args.next())?,
+				"--pusher-recovery" => this.pusher_recovery = value(&arg, args.next())?,
+				"--prefill" => this.prefill = value(&arg, args.next())?,
+				"--prefill-shelf" => this.prefill_shelf = value(&arg, args.next())?,
+				"--pegs" => {
+					let pattern: PegPattern = value(&arg, args.next())?;
+					pattern
+						.generate(PEG_FIELD)
+						.validate()
+						.map_err(|e| format!("Invalid peg pattern: {e}"))?;
+					this.pegs = Some(pattern);
+				}
+				"--pegs-file" => {
+					let path: PathBuf = value(&arg, args.next())?;
+					let layout = PegLayout::load(&path)
+						.map_err(|e| format!("Failed to load pegs from {path:?}: {e}"))?;
+					layout
+						.validate()
+						.map_err(|e| format!("Invalid pegs in {path:?}: {e}"))?;
+					this.pegs = Some(PegPattern::Custom(layout));
+				}
+				"--export-pegs" => this.export_pegs = Some(value(&arg, args.next())?),
+				"--jackpot-file" => this.jackpot_file = Some(value(&arg, args.next())?),
+				"--stop-after-jackpot" => this.stop.after_jackpot = true,
+				"--upgrades-file" => this.upgrades_file = Some(value(&arg, args.next())?),
+				"--session-file" => this.session_file = Some(value(&arg, args.next())?),
+				"--record" => this.record = Some(value(&arg, args.next())?),
+				"--replay" => this.replay = Some(value(&arg, args.next())?),
+				"--seed" => this.seed = Some(value(&arg, args.next())?),
+				"--max-coins" => this.stop.max_coins = Some(value(&arg, args.next())?),
+				"--max-spend" => this.stop.max_spend = Some(money(&arg, args.next())?),
+				"--stop-loss" => this.stop.stop_loss = Some(money(&arg, args.next())?),
+				"--take-profit" => this.stop.take_profit = Some(money(&arg, args.next())?),
+				_ => return Err(format!("Unknown argument `{arg}`")),
+			}
+		}
+		if this.record.is_some() && this.replay.is_some() {
+			return Err("Can't record while playing back a replay".into());
+		}
+		if (this.record.is_some() || this.replay.is_some())
+			&& (this.jackpot_file.is_some()
+				|| this.upgrades_file.is_some()
+				|| this.session_file.is_some())
+		{
+			// Playback would start from whatever they hold by then, not what was recorded
+			return Err("Replays can't use jackpot, upgrades or session files".into());
+		}
+		Ok(this)
+	}
+}
+
+fn value<T: FromStr>(arg: &str, val: Option<String>) -> Result<T, String>
+where
+	T::Err: std::fmt::Display,
+{
+	let val = val.ok_or_else(|| format!("`{arg}` needs a value"))?;
+	val.parse()
+		.map_err(|e| format!("Invalid value `{val}` for `{arg}`: {e}"))
+}
+
+fn money(arg: &str, val: Option<String>) -> Result<Currency, String> {
+	let val = val.ok_or_else(|| format!("`{arg}` needs a value"))?;
+	Currency::from_str(&val).map_err(|e| format!("Invalid amount `{val}` for `{arg}`: {e:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(args: &[&str]) -> Result<Options, String> {
+		Options::parse(args.iter().map(|arg| arg.to_string()))
+	}
+
+	#[test]
+	fn no_arguments_give_the_defaults() {
+		let options = parse(&[]).unwrap();
+		assert!(!options.headless);
+		assert_eq!(options.duration, Duration::from_secs(600));
+		assert_eq!(options.prefill, 0);
+	}
+
+	#[test]
+	fn arguments_take_their_values() {
+		let options = parse(&[
+			"--headless",
+			"--duration",
+			"1.5",
+			"--prefill",
+			"50",
+			"--prefill-shelf",
+			"10",
+			"--seed",
+			"7",
+		])
+		.unwrap();
+		assert!(options.headless);
+		assert_eq!(options.duration, Duration::from_secs_f32(1.5));
+		assert_eq!((options.prefill, options.prefill_shelf), (50, 10));
+		assert_eq!(options.seed, Some(7));
+	}
+
+	#[test]
+	fn bad_arguments_are_rejected() {
+		assert!(parse(&["--fly"]).is_err());
+		assert!(parse(&["--prefill"]).is_err());
+		assert!(parse(&["--prefill", "lots"]).is_err());
+	}
+
+	#[test]
+	fn pusher_jam_handling_is_configurable() {
+		let options = parse(&[
+			"--pusher-max-force",
+			"50",
+			"--pusher-jam-after",
+			"3",
+			"--pusher-recovery",
+			"retry:0.5",
+		])
+		.unwrap();
+		assert_eq!(options.pusher_max_force, Some(50.0));
+		assert_eq!(options.pusher_jam_after, 3.0);
+		assert_eq!(options.pusher_recovery, JamRecovery::Retry { delay: 0.5 });
+		assert!(parse(&["--pusher-recovery", "wiggle:1"]).is_err());
+	}
+
+	#[test]
+	fn replays_start_from_a_fresh_machine() {
+		assert!(parse(&["--record", "a.txt"]).is_ok());
+		assert!(parse(&["--record", "a.txt", "--replay", "b.txt"]).is_err());
+		assert!(parse(&["--replay", "b.txt", "--upgrades-file", "upgrades.txt"]).is_err());
+		assert!(parse(&["--record", "a.txt", "--session-file", "session.txt"]).is_err());
+	}
+}
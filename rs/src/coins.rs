@@ -1,4 +1,5 @@
 use crate::machine::DropZone;
+use crate::GameState;
 use avian3d::collision::{ColliderAabb, Collisions};
 use avian3d::math::PI;
 use avian3d::prelude::{AngularDamping, LinearDamping, Restitution};
@@ -7,8 +8,9 @@ use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
 use bevy::ecs::component::ComponentId;
 use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::*;
-use currency::{Currency, ParseCurrencyError};
-use rand::random;
+use currency::Currency;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::VecDeque;
 use std::ops::Not;
 use std::time::{Duration, Instant};
@@ -18,14 +20,19 @@ pub struct CoinsPlugin;
 impl Plugin for CoinsPlugin {
 	fn build(&self, app: &mut App) {
 		app.add_event::<DropCoin>()
+			.add_event::<CancelDrops>()
+			.add_event::<CoinShower>()
+			.init_resource::<PendingShower>()
 			.init_resource::<AutoDrop>()
 			.init_resource::<AutoDropTimer>()
-			.init_resource::<CoinQueue>()
+			.init_resource::<ActiveChute>()
+			.init_resource::<AutoDropChutes>()
 			.init_resource::<CoinCount>()
+			.init_resource::<DropRng>()
 			.add_systems(Startup, setup_coins)
 			.add_systems(
 				FixedUpdate,
-				(drop_coins, auto_drop_coins.run_if(AutoDrop::is_enabled)),
+				(drop_coins, shower_coins).run_if(in_state(GameState::Playing)),
 			);
 	}
 }
@@ -51,138 +58,302 @@ impl Default for Coin {
 }
 
 #[derive(Resource, Debug, Clone)]
-pub struct CoinScene(Handle<Scene>);
+pub struct CoinScene(pub Handle<Scene>);
+
+/// Everything a freshly spawned [`Coin`] needs besides its position.
+pub fn coin_bundle(coin: Coin, scene: &CoinScene, transform: Transform) -> impl Bundle {
+	(
+		coin,
+		SceneRoot(scene.0.clone()),
+		transform,
+		Restitution::new(1.0),
+		LinearDamping(0.05),
+		AngularDamping(0.05),
+	)
+}
 
 #[derive(Event, Debug, Clone)]
 pub struct DropCoin {
 	pub coin: Coin,
 	pub reason: CoinDropReason,
+	/// Index of the [`DropZone`] to drop into, or `None` for the [`ActiveChute`].
+	pub chute: Option<usize>,
+	/// Where across the chute to drop, from -1.0 (left edge) to 1.0 (right edge),
+	/// or `None` for anywhere.
+	pub aim: Option<f32>,
 }
 
 impl DropCoin {
-	pub fn auto(value: &str) -> Result<Self, ParseCurrencyError> {
-		Ok(Self {
-			coin: Coin {
-				value: Currency::from_str(value)?,
-			},
-			reason: CoinDropReason::Auto,
-		})
+	pub fn new(value: Currency, reason: CoinDropReason) -> Self {
+		Self {
+			coin: Coin { value },
+			reason,
+			chute: None,
+			aim: None,
+		}
 	}
 
-	pub fn manual(value: &str) -> Result<Self, ParseCurrencyError> {
-		Ok(Self {
-			coin: Coin {
-				value: Currency::from_str(value)?,
-			},
-			reason: CoinDropReason::Manual,
-		})
+	pub fn in_chute(self, chute: usize) -> Self {
+		Self {
+			chute: Some(chute),
+			..self
+		}
+	}
+
+	pub fn aimed(self, aim: f32) -> Self {
+		Self {
+			aim: Some(aim),
+			..self
+		}
 	}
 }
 
+/// Removes every queued drop with the given reason that hasn't spawned yet.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CancelDrops(pub CoinDropReason);
+
 #[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CoinDropReason {
 	Auto,
 	Manual,
+	/// Free coins from bonuses. They don't count as money put in.
+	Bonus,
 }
 
-fn drop_coins(
+pub fn drop_coins(
 	mut cmds: Commands,
 	mut events: ResMut<Events<DropCoin>>,
-	drop_zone: Single<(Entity, &GlobalTransform, &ColliderAabb), With<DropZone>>,
+	mut drop_zones: Query<(
+		Entity,
+		&DropZone,
+		&GlobalTransform,
+		&ColliderAabb,
+		&mut CoinQueue,
+	)>,
 	collisions: Res<Collisions>,
 	coins: Query<Entity, With<Coin>>,
 	coin_scene: Res<CoinScene>,
 	diags: Res<DiagnosticsStore>,
-	mut queue: ResMut<CoinQueue>,
 	mut last_fps_warn: Local<Option<Instant>>,
 	mut auto_drop_timer: ResMut<AutoDropTimer>,
 	auto_drop: Res<AutoDrop>,
+	active_chute: Res<ActiveChute>,
+	mut cancels: EventReader<CancelDrops>,
+	mut rng: ResMut<DropRng>,
 ) {
 	if auto_drop.is_changed() && !**auto_drop {
 		// Would be confusing to keep auto-dropping after it is disabled.
-		queue.retain(|ev| ev.reason != CoinDropReason::Auto);
+		for (.., mut queue) in &mut drop_zones {
+			queue.retain(|ev| ev.reason != CoinDropReason::Auto);
+		}
+	}
+
+	for ev in events.drain() {
+		let chute = ev.chute.unwrap_or(**active_chute);
+		let Some((.., mut queue)) = drop_zones.iter_mut().find(|(_, dz, ..)| dz.0 == chute) else {
+			warn!(chute, "No such chute, skipping {ev:?}");
+			continue;
+		};
+		if queue.len() <= 100 {
+			queue.push_back(ev);
+		} else {
+			warn!("Queue is full, skipping {ev:?}");
+		}
+	}
+
+	for CancelDrops(reason) in cancels.read() {
+		info!(?reason, "Cancelling queued drops");
+		for (.., mut queue) in &mut drop_zones {
+			queue.retain(|ev| ev.reason != *reason);
+		}
+	}
+
+	if drop_zones.iter().all(|(.., queue)| queue.is_empty()) {
+		return;
+	}
+
+	if too_slow(&diags, &mut last_fps_warn) {
+		return;
 	}
 
-	let dz = drop_zone.2.size();
-	let dz_center = drop_zone.2.center(); // AABB is in global coords
 	let coin_dia = 2.0;
-	let h_range = dz.x - coin_dia;
-	let v_range = dz.y - coin_dia;
-
-	let mut events = events.drain();
-
-	let ev = events.next();
-	let mut to_spawn = queue.drain(..).chain(ev);
-	let failed_coin = (|| {
-		let DropCoin { coin, reason } = to_spawn.next()?;
-		let h = random::<f32>() * h_range - (0.5 * h_range);
-		let v = random::<f32>() * v_range - (0.5 * v_range);
-
-		if let Some(fps) = diags.get(&FrameTimeDiagnosticsPlugin::FPS) {
-			if let Some(fps) = fps.smoothed() {
-				if fps < 24.0 {
-					let now = Instant::now();
-					let last_warn = if let Some(last_fps_warn) = &*last_fps_warn {
-						now.duration_since(*last_fps_warn)
-					} else {
-						Duration::MAX
-					};
-					if last_warn.as_secs() >= 1 {
-						let last_warn = last_fps_warn.map(|_| last_warn);
-						warn!(
-							fps,
-							?last_warn,
-							"Performance is too low, not spawning another coin."
-						);
-						*last_fps_warn = Some(now);
-					}
-					return Some(DropCoin { coin, reason });
-				}
-			}
+	'zones: for (id, dz, xform, aabb, mut queue) in &mut drop_zones {
+		if queue.is_empty() {
+			continue;
 		}
-		for col in collisions.collisions_with_entity(drop_zone.0) {
+		for col in collisions.collisions_with_entity(id) {
 			if coins.contains(col.entity1) || coins.contains(col.entity2) {
 				// Another coin might overlap, wait for it to clear
 				//     *alternatively, we could  try to spawn beside any coins in the drop zone
-				trace!("Not spawning because another coin is in the drop zone");
-				return Some(DropCoin { coin, reason });
+				trace!(
+					chute = dz.0,
+					"Not spawning because another coin is in the drop zone"
+				);
+				continue 'zones;
 			}
 		}
-		info!(?h, ?v, ?reason, "Dropping coin...");
+		let Some(DropCoin {
+			coin, reason, aim, ..
+		}) = queue.pop_front()
+		else {
+			continue;
+		};
+
+		let size = aabb.size();
+		let center = aabb.center(); // AABB is in global coords
+		let h_range = size.x - coin_dia;
+		let v_range = size.y - coin_dia;
+		let h = aim.map_or_else(
+			|| rng.0.gen::<f32>() - 0.5,
+			|aim| 0.5 * aim.clamp(-1.0, 1.0),
+		) * h_range;
+		let v = rng.0.gen::<f32>() * v_range - (0.5 * v_range);
+
+		info!(?h, ?v, ?reason, chute = dz.0, "Dropping coin...");
 		cmds.spawn((
-			coin,
-			SceneRoot(coin_scene.0.clone()),
-			Transform {
-				translation: Vec3::new(dz_center.x + h, dz_center.y, dz_center.z + v),
-				rotation: drop_zone.1.rotation() * Quat::from_rotation_x(PI),
-				..default()
-			},
-			Restitution::new(1.0),
-			LinearDamping(0.05),
-			AngularDamping(0.05),
+			coin_bundle(
+				coin,
+				&coin_scene,
+				Transform {
+					translation: Vec3::new(center.x + h, center.y, center.z + v),
+					rotation: xform.rotation() * Quat::from_rotation_x(PI),
+					..default()
+				},
+			),
+			reason,
 		));
 		auto_drop_timer.reset();
-		None
-	})();
+	}
+}
+
+/// Whether the game is running too slowly to add more coins. Warns at most once a second.
+fn too_slow(diags: &DiagnosticsStore, last_fps_warn: &mut Option<Instant>) -> bool {
+	let Some(fps) = diags
+		.get(&FrameTimeDiagnosticsPlugin::FPS)
+		.and_then(|fps| fps.smoothed())
+	else {
+		return false;
+	};
+	if fps >= 24.0 {
+		return false;
+	}
+	let now = Instant::now();
+	let last_warn = if let Some(last_fps_warn) = &*last_fps_warn {
+		now.duration_since(*last_fps_warn)
+	} else {
+		Duration::MAX
+	};
+	if last_warn.as_secs() >= 1 {
+		let last_warn = last_fps_warn.map(|_| last_warn);
+		warn!(
+			fps,
+			?last_warn,
+			"Performance is too low, not spawning another coin."
+		);
+		*last_fps_warn = Some(now);
+	}
+	true
+}
 
-	drop(to_spawn);
+/// Dumps `count` coins onto the platform from above, across its whole width. They skip
+/// the drop zones, so they don't wait in line behind other drops.
+#[derive(Event, Debug, Clone)]
+pub struct CoinShower {
+	pub count: usize,
+	pub value: Currency,
+}
 
-	if let Some(failed) = failed_coin {
-		queue.push_front(failed);
+impl CoinShower {
+	pub fn new(count: usize, value: Currency) -> Self {
+		Self { count, value }
 	}
+}
 
-	for ev in events {
-		if queue.len() <= 100 {
-			queue.push_back(ev);
-		} else {
-			warn!("Queue is full, skipping {ev:?}");
+/// Coins from [`CoinShower`]s that haven't spawned yet.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PendingShower(VecDeque<Currency>);
+
+/// Most shower coins spawned in one tick, side by side in a row.
+pub const SHOWER_ROW: usize = 8;
+/// How far a shower coin spawns from any other coin, center to center. A little more than
+/// a coin's diameter, whichever way up they are.
+const SHOWER_CLEARANCE: f32 = 2.1;
+
+pub fn shower_coins(
+	mut cmds: Commands,
+	mut events: EventReader<CoinShower>,
+	mut pending: ResMut<PendingShower>,
+	coin_scene: Res<CoinScene>,
+	diags: Res<DiagnosticsStore>,
+	mut last_fps_warn: Local<Option<Instant>>,
+	mut rng: ResMut<DropRng>,
+	coins: Query<&GlobalTransform, With<Coin>>,
+) {
+	for ev in events.read() {
+		info!(count = ev.count, "Coin shower!");
+		pending
+			.0
+			.extend(std::iter::repeat_n(ev.value.clone(), ev.count));
+	}
+	if pending.0.is_empty() || too_slow(&diags, &mut last_fps_warn) {
+		return;
+	}
+	// Each coin gets its own slot across the platform so they can't overlap
+	let slot = 18.0 / SHOWER_ROW as f32;
+	for i in 0..SHOWER_ROW {
+		if pending.0.is_empty() {
+			break;
 		}
+		let jitter = (rng.0.gen::<f32>() - 0.5) * (slot - 2.0).max(0.0);
+		let translation = Vec3::new(
+			-9.0 + (i as f32 + 0.5) * slot + jitter,
+			rng.0.gen::<f32>() * 10.0,
+			16.0,
+		);
+		// The last row has barely fallen in one tick. Like a drop zone, wait for it to clear
+		// rather than spawning inside it and having the solver fling both coins apart.
+		if coins
+			.iter()
+			.any(|xform| xform.translation().distance(translation) < SHOWER_CLEARANCE)
+		{
+			continue;
+		}
+		let Some(value) = pending.0.pop_front() else {
+			break;
+		};
+		cmds.spawn((
+			coin_bundle(
+				Coin { value },
+				&coin_scene,
+				Transform {
+					translation,
+					rotation: Quat::from_rotation_x(rng.0.gen::<f32>() * PI),
+					..default()
+				},
+			),
+			CoinDropReason::Bonus,
+		));
 	}
 }
 
-#[derive(Resource, Debug, Clone, Default, Deref, DerefMut)]
+/// Drops waiting for their [`DropZone`] to clear.
+#[derive(Component, Debug, Clone, Default, Deref, DerefMut)]
 pub struct CoinQueue(VecDeque<DropCoin>);
 
+/// The chute that drops without an explicit [`DropCoin::chute`] go to.
+#[derive(Resource, Default, Debug, Copy, Clone, PartialEq, Eq, Deref, DerefMut)]
+pub struct ActiveChute(pub usize);
+
+/// Which chute auto-drops go to.
+#[derive(Resource, Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AutoDropChutes {
+	/// Always use the [`ActiveChute`].
+	#[default]
+	Active,
+	/// Take turns through every chute in order.
+	Cycle,
+}
+
 #[derive(Resource, Default, Debug, Copy, Clone, PartialEq, Eq, Deref, DerefMut)]
 pub struct AutoDrop(bool);
 
@@ -210,14 +381,20 @@ impl Default for AutoDropTimer {
 	}
 }
 
-pub fn auto_drop_coins(
-	mut events: EventWriter<DropCoin>,
-	mut timer: ResMut<AutoDropTimer>,
-	t: Res<Time>,
-) {
-	timer.tick(t.delta());
-	if timer.finished() {
-		events.send(DropCoin::auto("$1.00").unwrap());
+/// Rolls where coins drop, including showers, and when random auto-drops happen. Seeded
+/// from the run's seed, so that replays and `--seed` runs drop the same way every time.
+#[derive(Resource, Debug, Clone)]
+pub struct DropRng(pub StdRng);
+
+impl DropRng {
+	pub fn new(seed: u64) -> Self {
+		Self(StdRng::seed_from_u64(seed))
+	}
+}
+
+impl Default for DropRng {
+	fn default() -> Self {
+		Self(StdRng::from_entropy())
 	}
 }
 
@@ -0,0 +1,106 @@
+use crate::machine::collect;
+use bevy::prelude::*;
+
+/// Coins collected in quick succession build up a multiplier on what they pay. Configured
+/// by [`crate::machine::MachineConfig::combo`], applied by [`collect`].
+pub struct CombosPlugin;
+
+impl Plugin for CombosPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Combo>()
+			.add_event::<ComboEnded>()
+			.add_systems(Update, end_combos.before(collect));
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ComboConfig {
+	/// Seconds after a collection that the next one still counts towards the combo.
+	pub window: f32,
+	/// Added to the multiplier by every coin after the first.
+	pub step: f32,
+	pub max_factor: f32,
+}
+
+impl Default for ComboConfig {
+	fn default() -> Self {
+		Self {
+			window: 1.0,
+			step: 0.1,
+			max_factor: 3.0,
+		}
+	}
+}
+
+impl ComboConfig {
+	pub fn factor(&self, count: u32) -> f32 {
+		(1.0 + self.step * count.saturating_sub(1) as f32).min(self.max_factor)
+	}
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Combo {
+	/// Coins collected in the current combo, or 0 if there isn't one.
+	pub count: u32,
+	/// Seconds left for the next coin to keep the combo going.
+	pub secs: f32,
+	/// Multiplier the last coin was paid with.
+	pub factor: f32,
+}
+
+impl Combo {
+	/// Counts a collected coin, and returns what to multiply its value by.
+	pub fn hit(&mut self, config: &ComboConfig) -> f32 {
+		self.count += 1;
+		self.secs = config.window;
+		self.factor = config.factor(self.count);
+		self.factor
+	}
+}
+
+/// Sent when a combo runs out of time.
+#[derive(Event, Debug, Copy, Clone)]
+pub struct ComboEnded {
+	pub size: u32,
+}
+
+pub fn end_combos(mut combo: ResMut<Combo>, mut ended: EventWriter<ComboEnded>, t: Res<Time>) {
+	if combo.count == 0 {
+		return;
+	}
+	combo.secs -= t.delta_secs();
+	if combo.secs > 0.0 {
+		return;
+	}
+	if combo.count > 1 {
+		info!(size = combo.count, factor = combo.factor, "Combo ended");
+	}
+	ended.send(ComboEnded { size: combo.count });
+	*combo = Combo::default();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn factor_builds_up_to_the_max() {
+		let config = ComboConfig::default();
+		assert_eq!(config.factor(0), 1.0);
+		assert_eq!(config.factor(1), 1.0);
+		assert!((config.factor(3) - 1.2).abs() < 1e-6);
+		assert_eq!(config.factor(100), config.max_factor);
+	}
+
+	#[test]
+	fn hit_counts_and_restarts_the_window() {
+		let config = ComboConfig::default();
+		let mut combo = Combo::default();
+		combo.hit(&config);
+		combo.secs = 0.2;
+		let factor = combo.hit(&config);
+		assert_eq!(combo.count, 2);
+		assert_eq!(combo.secs, config.window);
+		assert!((factor - 1.1).abs() < 1e-6);
+	}
+}
@@ -0,0 +1,155 @@
+use crate::cents;
+use crate::coins::Coin;
+use crate::lanes::LaneHistogram;
+use crate::machine::InTray;
+use crate::prizes::PrizeInventory;
+use crate::reels::{ReelConfig, Reels};
+use crate::stats::PayoutStats;
+use bevy::app::{PluginsState, ScheduleRunnerPlugin};
+use bevy::prelude::*;
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+use std::time::{Duration, Instant};
+
+/// [`DefaultPlugins`] without a window or renderer, so that machines can be simulated
+/// as fast as the CPU allows. Every [`App::update`] advances exactly one fixed tick.
+pub fn headless_plugins() -> impl PluginGroup {
+	DefaultPlugins
+		.set(WindowPlugin {
+			primary_window: None,
+			exit_condition: ExitCondition::DontExit,
+			..default()
+		})
+		.set(RenderPlugin {
+			render_creation: WgpuSettings {
+				backends: None,
+				..default()
+			}
+			.into(),
+			..default()
+		})
+		.disable::<WinitPlugin>()
+		.add(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+}
+
+pub fn fixed_step_time() -> TimeUpdateStrategy {
+	TimeUpdateStrategy::ManualDuration(Time::<Fixed>::default().timestep())
+}
+
+/// Summary of a headless run.
+#[derive(Debug, Clone)]
+pub struct Report {
+	pub simulated: Duration,
+	/// Wall-clock time the simulation took.
+	pub cost: Duration,
+	pub stats: PayoutStats,
+	/// Coins still on the machine at the end, not counting the payout tray.
+	pub coins_on_bed: usize,
+	pub lanes: LaneHistogram,
+	/// The reels' symbol table, to compare against `stats.reel_wins`.
+	pub reels: Option<ReelConfig>,
+	pub prizes: PrizeInventory,
+}
+
+impl Report {
+	/// Collected value and bonus credit over dropped value, or 0.0 if nothing was dropped.
+	pub fn payout_ratio(&self) -> f64 {
+		let dropped = cents(&self.stats.value_dropped);
+		if dropped == 0 {
+			0.0
+		} else {
+			let paid = cents(&self.stats.value_collected) + cents(&self.stats.value_bonus);
+			paid as f64 / dropped as f64
+		}
+	}
+}
+
+/// Steps `app` until `duration` of simulated time has passed.
+pub fn simulate(app: &mut App, duration: Duration) -> Report {
+	simulate_with(app, duration, |_| {})
+}
+
+/// Like [`simulate`], calling `each_tick` with the world after every tick.
+pub fn simulate_with(
+	app: &mut App,
+	duration: Duration,
+	mut each_tick: impl FnMut(&mut World),
+) -> Report {
+	while app.plugins_state() == PluginsState::Adding {
+		bevy::tasks::tick_global_task_pools_on_main_thread();
+	}
+	app.finish();
+	app.cleanup();
+
+	let start = Instant::now();
+	while app.world().resource::<Time<Virtual>>().elapsed() < duration {
+		app.update();
+		each_tick(app.world_mut());
+	}
+
+	let world = app.world_mut();
+	let coins_on_bed = world
+		.query_filtered::<(), (With<Coin>, Without<InTray>)>()
+		.iter(world)
+		.count();
+	Report {
+		simulated: world.resource::<Time<Virtual>>().elapsed(),
+		cost: start.elapsed(),
+		stats: world.resource::<PayoutStats>().clone(),
+		coins_on_bed,
+		lanes: world.resource::<LaneHistogram>().clone(),
+		reels: world
+			.get_resource::<Reels>()
+			.map(|reels| reels.config.clone()),
+		prizes: world.resource::<PrizeInventory>().clone(),
+	}
+}
+
+impl std::fmt::Display for Report {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "Simulated:       {:?}", self.simulated)?;
+		writeln!(f, "Took:            {:?}", self.cost)?;
+		writeln!(
+			f,
+			"Dropped:         {} coins, {}",
+			self.stats.coins_dropped, self.stats.value_dropped
+		)?;
+		writeln!(
+			f,
+			"Collected:       {} coins, {}",
+			self.stats.coins_collected, self.stats.value_collected
+		)?;
+		writeln!(f, "Special coins:   {}", self.stats.special_collected)?;
+		writeln!(f, "Bonus coins:     {}", self.stats.bonus_coins)?;
+		writeln!(f, "Bonus credit:    {}", self.stats.value_bonus)?;
+		writeln!(f, "Pocket hits:     {:?}", self.stats.pocket_hits)?;
+		writeln!(f, "Jackpots won:    {}", self.stats.jackpots_won)?;
+		writeln!(
+			f,
+			"Reel spins:      {}, {} won",
+			self.stats.reel_spins, self.stats.reel_wins
+		)?;
+		if let Some(reels) = &self.reels {
+			writeln!(f, "Reel odds:\n{reels}")?;
+		}
+		writeln!(
+			f,
+			"Combos:          best {}, by size {:?}",
+			self.stats.best_combo(),
+			self.stats.combo_sizes
+		)?;
+		writeln!(
+			f,
+			"Power-ups:       {}, {} spent",
+			self.stats.power_ups_bought, self.stats.value_spent
+		)?;
+		writeln!(f, "Prizes:          {}", self.prizes)?;
+		writeln!(f, "Payout ratio:    {:.3}", self.payout_ratio())?;
+		writeln!(f, "Coins on bed:    {}", self.coins_on_bed)?;
+		writeln!(f, "Exit lanes:      {} coins", self.lanes.total())?;
+		write!(f, "{}", self.lanes)
+	}
+}
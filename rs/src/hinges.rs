@@ -0,0 +1,152 @@
+use crate::coins::drop_coins;
+use crate::GameState;
+use avian3d::prelude::{AngularVelocity, Collider, GravityScale, RigidBody};
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// Flippers and gates hinged to the peg board. Spawned by [`crate::machine::spawn_machine`]
+/// from [`crate::machine::MachineConfig::hinges`].
+pub struct HingesPlugin;
+
+impl Plugin for HingesPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_event::<Flip>().add_systems(
+			FixedUpdate,
+			(flip, drive_hinges)
+				.chain()
+				.before(drop_coins)
+				.run_if(in_state(GameState::Playing)),
+		);
+	}
+}
+
+/// Seconds a flipper stays up after [`Flip`].
+pub const FLIP_SECS: f32 = 0.3;
+
+#[derive(Debug, Clone)]
+pub struct HingeConfig {
+	/// Where it's hinged, as (across, up) from the center of the peg board.
+	pub pivot: Vec2,
+	pub length: f32,
+	/// Angle it springs back to, in radians counterclockwise from pointing right.
+	pub rest_angle: f32,
+	/// How hard it springs back, as angular acceleration per radian away from its target.
+	pub stiffness: f32,
+	pub damping: f32,
+	pub kind: HingeKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HingeKind {
+	/// Swings to `flipped_angle` for [`FLIP_SECS`] when the player sends [`Flip`].
+	Flipper { flipped_angle: f32 },
+	/// Only moves when coins push it.
+	Gate,
+}
+
+impl HingeConfig {
+	pub fn flipper(pivot: Vec2, length: f32, rest_angle: f32, flipped_angle: f32) -> Self {
+		Self {
+			pivot,
+			length,
+			rest_angle,
+			stiffness: 600.0,
+			damping: 40.0,
+			kind: HingeKind::Flipper { flipped_angle },
+		}
+	}
+
+	/// A gate that coins can push open, but only just.
+	pub fn gate(pivot: Vec2, length: f32, rest_angle: f32) -> Self {
+		Self {
+			pivot,
+			length,
+			rest_angle,
+			stiffness: 40.0,
+			damping: 6.0,
+			kind: HingeKind::Gate,
+		}
+	}
+}
+
+/// Spring on a jointed body that pulls it toward its rest angle, or its flipped angle
+/// while it is flipped.
+#[derive(Component, Debug, Clone)]
+#[require(RigidBody, Collider, GravityScale(|| GravityScale(0.0)), AngularVelocity)]
+pub struct Hinge {
+	/// Index into [`crate::machine::MachineConfig::hinges`].
+	pub index: usize,
+	/// Rotation of the board it is hinged to. Angles are measured in its XZ plane.
+	pub board: Quat,
+	pub rest_angle: f32,
+	pub stiffness: f32,
+	pub damping: f32,
+	pub kind: HingeKind,
+	/// Seconds left until a flipper falls back.
+	pub flipped_for: f32,
+}
+
+impl Hinge {
+	pub fn new(index: usize, board: Quat, config: &HingeConfig) -> Self {
+		Self {
+			index,
+			board,
+			rest_angle: config.rest_angle,
+			stiffness: config.stiffness,
+			damping: config.damping,
+			kind: config.kind,
+			flipped_for: 0.0,
+		}
+	}
+
+	/// Current angle, like [`HingeConfig::rest_angle`].
+	pub fn angle(&self, rotation: Quat) -> f32 {
+		let dir = (self.board.inverse() * rotation) * Vec3::X;
+		dir.z.atan2(dir.x)
+	}
+
+	/// World space axis that angles increase around.
+	pub fn axis(&self) -> Vec3 {
+		self.board * Vec3::NEG_Y
+	}
+}
+
+/// Rotation, relative to the board, of a hinge at `angle`.
+pub fn hinge_rotation(angle: f32) -> Quat {
+	Quat::from_rotation_y(-angle)
+}
+
+/// Flips the flipper at this index into [`crate::machine::MachineConfig::hinges`]. Sent by
+/// the player, or played back from a [`crate::replay::Replay`].
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Flip(pub usize);
+
+pub fn flip(mut events: EventReader<Flip>, mut hinges: Query<&mut Hinge>) {
+	for &Flip(index) in events.read() {
+		let Some(mut hinge) = hinges.iter_mut().find(|hinge| hinge.index == index) else {
+			warn!(index, "No such hinge, can't flip it");
+			continue;
+		};
+		if matches!(hinge.kind, HingeKind::Flipper { .. }) {
+			hinge.flipped_for = FLIP_SECS;
+		}
+	}
+}
+
+pub fn drive_hinges(mut q: Query<(&mut Hinge, &Transform, &mut AngularVelocity)>, t: Res<Time>) {
+	let dt = t.delta_secs();
+	for (mut hinge, xform, mut ang_vel) in &mut q {
+		hinge.flipped_for = (hinge.flipped_for - dt).max(0.0);
+		let target = match hinge.kind {
+			HingeKind::Flipper { flipped_angle } if hinge.flipped_for > 0.0 => flipped_angle,
+			_ => hinge.rest_angle,
+		};
+		// Shortest way around, so angles either side of pointing left don't fight
+		let error = (target - hinge.angle(xform.rotation) + PI).rem_euclid(TAU) - PI;
+		let axis = hinge.axis();
+		let speed = ang_vel.0.dot(axis);
+		// Acceleration rather than torque, so it behaves the same whatever the hinge weighs.
+		// Coins still push it around in between.
+		ang_vel.0 += axis * (hinge.stiffness * error - hinge.damping * speed) * dt;
+	}
+}
@@ -0,0 +1,104 @@
+use crate::coins::{CoinQueue, CoinScene};
+use crate::machine::{DropZone, MachineConfig};
+use avian3d::math::FRAC_PI_2;
+use bevy::prelude::*;
+
+pub struct HopperPlugin;
+
+impl Plugin for HopperPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Startup, spawn_hopper)
+			.add_systems(Update, sync_hopper_coins);
+	}
+}
+
+const HOPPER_SIZE: Vec3 = Vec3::new(20.0, 6.0, 8.0);
+const COIN_THICKNESS: f32 = 0.25;
+/// More than this many queued coins in one chute just fill the column to the top.
+const MAX_SHOWN_PER_CHUTE: usize = 28;
+
+/// Glass box above the rear board that shows the contents of every [`CoinQueue`].
+#[derive(Component, Debug)]
+#[require(Transform, Visibility)]
+pub struct Hopper;
+
+/// Purely visual stand-in for a queued coin. Has no physics.
+#[derive(Component, Debug)]
+pub struct HopperCoin {
+	pub chute: usize,
+	pub slot: usize,
+}
+
+pub fn spawn_hopper(
+	mut cmds: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut mats: ResMut<Assets<StandardMaterial>>,
+	config: Res<MachineConfig>,
+) {
+	if !config.hopper {
+		return;
+	}
+	cmds.spawn((
+		Hopper,
+		Mesh3d(meshes.add(Cuboid::from_size(HOPPER_SIZE))),
+		MeshMaterial3d(mats.add(StandardMaterial {
+			base_color: Color::srgba(0.8, 0.9, 1.0, 0.12),
+			alpha_mode: AlphaMode::Blend,
+			reflectance: 0.8,
+			..default()
+		})),
+		// Just above the top of the rear board
+		Transform::from_translation(Vec3::new(0.0, 19.0, 49.0)),
+	));
+}
+
+/// Columns are spread evenly across the hopper, one per chute. The stacks shrink from
+/// the top as coins are fed into their chutes.
+pub fn sync_hopper_coins(
+	mut cmds: Commands,
+	hopper: Option<Single<Entity, With<Hopper>>>,
+	queues: Query<(&DropZone, Ref<CoinQueue>)>,
+	hopper_coins: Query<(Entity, &HopperCoin)>,
+	coin_scene: Res<CoinScene>,
+	config: Res<MachineConfig>,
+) {
+	let Some(hopper) = hopper else {
+		return;
+	};
+	let column_width = HOPPER_SIZE.x / config.chutes.len().max(1) as f32;
+	for (dz, queue) in &queues {
+		if !queue.is_changed() {
+			continue;
+		}
+		let wanted = queue.len().min(MAX_SHOWN_PER_CHUTE);
+		let mut shown = 0;
+		for (id, coin) in &hopper_coins {
+			if coin.chute != dz.0 {
+				continue;
+			}
+			if coin.slot < wanted {
+				shown += 1;
+			} else {
+				cmds.entity(id).despawn_recursive();
+			}
+		}
+		let x = (dz.0 as f32 + 0.5) * column_width - 0.5 * HOPPER_SIZE.x;
+		cmds.entity(*hopper).with_children(|cmds| {
+			for slot in shown..wanted {
+				cmds.spawn((
+					HopperCoin { chute: dz.0, slot },
+					SceneRoot(coin_scene.0.clone()),
+					Transform {
+						translation: Vec3::new(
+							x,
+							0.0,
+							(slot as f32 + 0.5) * COIN_THICKNESS - 0.5 * HOPPER_SIZE.z,
+						),
+						rotation: Quat::from_rotation_x(FRAC_PI_2),
+						..default()
+					},
+				));
+			}
+		});
+	}
+}
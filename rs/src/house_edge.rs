@@ -0,0 +1,222 @@
+use crate::cents;
+use crate::coins::{Coin, CoinDropReason};
+use crate::machine::{CoinCollected, Piston};
+use crate::GameState;
+use avian3d::prelude::Friction;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+/// Steers the machine toward a target hold by nudging [`Tunable`] parameters.
+///
+/// Only added when a target is configured. Every adjustment is logged, kept in
+/// [`HouseEdge::log`], and appended to [`HouseEdge::audit_file`] if there is one,
+/// so that the behavior can be disclosed.
+pub struct HouseEdgePlugin;
+
+impl Plugin for HouseEdgePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			FixedUpdate,
+			(record_drops, record_collections, adjust)
+				.chain()
+				.run_if(resource_exists::<HouseEdge>.and(in_state(GameState::Playing))),
+		);
+	}
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct HouseEdge {
+	/// Fraction of money put in that the house should keep, e.g. 0.1 for 10%.
+	pub target_hold: f32,
+	/// Seconds of history the payout ratio is measured over.
+	pub window: f32,
+	/// Seconds between adjustments.
+	pub interval: f32,
+	/// Don't adjust anything until at least this many coins were dropped in the window.
+	pub min_samples: usize,
+	/// Relative change per unit of hold error, e.g. 0.5 changes parameters by 5%
+	/// when hold is off by 10 points.
+	pub gain: f32,
+	pub tunables: Vec<Tunable>,
+	pub audit_file: Option<PathBuf>,
+	pub log: Vec<Adjustment>,
+	/// `(time, dropped cents, collected cents)`
+	history: VecDeque<(f32, i64, i64)>,
+	last_adjustment: f32,
+}
+
+impl HouseEdge {
+	pub fn new(target_hold: f32, audit_file: Option<PathBuf>) -> Self {
+		Self {
+			target_hold,
+			window: 300.0,
+			interval: 60.0,
+			min_samples: 30,
+			gain: 0.5,
+			tunables: vec![
+				Tunable {
+					param: TunableParam::PistonSpeed,
+					bounds: 0.5..=1.5,
+				},
+				Tunable {
+					param: TunableParam::PlatformFriction,
+					bounds: 0.6..=1.2,
+				},
+			],
+			audit_file,
+			log: Vec::new(),
+			history: VecDeque::new(),
+			last_adjustment: 0.0,
+		}
+	}
+
+	/// Collected over dropped in the current window, and how many coins were dropped.
+	pub fn payout_ratio(&self) -> (f32, usize) {
+		let (mut dropped, mut collected, mut count) = (0, 0, 0);
+		for &(_, d, c) in &self.history {
+			dropped += d;
+			collected += c;
+			if d > 0 {
+				count += 1;
+			}
+		}
+		if dropped == 0 {
+			(0.0, count)
+		} else {
+			(collected as f32 / dropped as f32, count)
+		}
+	}
+}
+
+/// A parameter the controller may change, and the range it must stay within.
+#[derive(Debug, Clone)]
+pub struct Tunable {
+	pub param: TunableParam,
+	pub bounds: RangeInclusive<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TunableParam {
+	/// [`Piston::speed`]. Faster pushes more coins off.
+	PistonSpeed,
+	/// Friction of the piston's top. More carries more coins forward.
+	PlatformFriction,
+}
+
+#[derive(Debug, Clone)]
+pub struct Adjustment {
+	pub time: f32,
+	pub payout_ratio: f32,
+	pub target_hold: f32,
+	pub param: TunableParam,
+	pub old: f32,
+	pub new: f32,
+}
+
+pub fn record_drops(
+	mut edge: ResMut<HouseEdge>,
+	dropped: Query<(&Coin, &CoinDropReason), Added<Coin>>,
+	t: Res<Time>,
+) {
+	for (coin, reason) in &dropped {
+		if *reason == CoinDropReason::Bonus {
+			continue;
+		}
+		edge.history
+			.push_back((t.elapsed_secs(), cents(&coin.value), 0));
+	}
+}
+
+pub fn record_collections(
+	mut edge: ResMut<HouseEdge>,
+	mut events: EventReader<CoinCollected>,
+	t: Res<Time>,
+) {
+	for ev in events.read() {
+		if !ev.prefilled {
+			edge.history
+				.push_back((t.elapsed_secs(), 0, cents(&ev.paid)));
+		}
+	}
+}
+
+pub fn adjust(
+	mut edge: ResMut<HouseEdge>,
+	mut pistons: Query<(&mut Piston, &mut Friction)>,
+	t: Res<Time>,
+) {
+	let now = t.elapsed_secs();
+	let window_start = now - edge.window;
+	while edge
+		.history
+		.front()
+		.is_some_and(|&(time, ..)| time < window_start)
+	{
+		edge.history.pop_front();
+	}
+	if now - edge.last_adjustment < edge.interval {
+		return;
+	}
+	edge.last_adjustment = now;
+
+	let (payout_ratio, samples) = edge.payout_ratio();
+	if samples < edge.min_samples {
+		return;
+	}
+	// Positive when the machine is paying out too much
+	let error = edge.target_hold - (1.0 - payout_ratio);
+	let scale = 1.0 - edge.gain * error;
+
+	let mut adjustments = Vec::new();
+	for (mut piston, mut friction) in &mut pistons {
+		for tunable in &edge.tunables {
+			let value = match tunable.param {
+				TunableParam::PistonSpeed => &mut piston.speed,
+				TunableParam::PlatformFriction => &mut friction.dynamic_coefficient,
+			};
+			let old = *value;
+			let new = (old * scale).clamp(*tunable.bounds.start(), *tunable.bounds.end());
+			if new == old {
+				continue;
+			}
+			*value = new;
+			adjustments.push(Adjustment {
+				time: now,
+				payout_ratio,
+				target_hold: edge.target_hold,
+				param: tunable.param,
+				old,
+				new,
+			});
+		}
+		friction.static_coefficient = friction.dynamic_coefficient;
+	}
+
+	for adjustment in adjustments {
+		info!(?adjustment, "House edge adjustment");
+		if let Some(path) = &edge.audit_file {
+			let line = format!(
+				"{:.3},{:.4},{:.4},{:?},{},{}\n",
+				adjustment.time,
+				adjustment.payout_ratio,
+				adjustment.target_hold,
+				adjustment.param,
+				adjustment.old,
+				adjustment.new
+			);
+			let result = OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(path)
+				.and_then(|mut file| file.write_all(line.as_bytes()));
+			if let Err(e) = result {
+				error!(?path, "Failed to write house edge audit log: {e}");
+			}
+		}
+		edge.log.push(adjustment);
+	}
+}
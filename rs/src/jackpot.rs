@@ -0,0 +1,180 @@
+use crate::coins::{Coin, CoinDropReason, CoinShower};
+use crate::machine::{CoinCollected, MachineConfig};
+use crate::pockets::{PocketHit, Reward};
+use crate::{cents, from_cents, GameState, Winnings};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use currency::Currency;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A pool fed by every dropped coin, won through a [`Reward::Jackpot`] pocket or by luck
+/// when collecting. Configured by [`MachineConfig::jackpot`].
+pub struct JackpotPlugin;
+
+impl Plugin for JackpotPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_event::<JackpotWon>()
+			.add_systems(
+				FixedUpdate,
+				(feed_jackpot, win_jackpot)
+					.chain()
+					.run_if(resource_exists::<Jackpot>.and(in_state(GameState::Playing))),
+			)
+			.add_systems(Last, save_jackpot.run_if(resource_exists::<Jackpot>));
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct JackpotConfig {
+	/// Fraction of each dropped coin's value that goes into the pool.
+	pub contribution: f64,
+	/// What the pool starts at, and goes back to after it's won.
+	pub seed: Currency,
+	/// Chance that any one collected coin wins the pool.
+	pub lottery_odds: f64,
+	/// Coins showered onto the platform when it's won, on top of the pool.
+	pub shower: usize,
+}
+
+impl Default for JackpotConfig {
+	fn default() -> Self {
+		Self {
+			contribution: 0.05,
+			seed: Currency::from_str("$10.00").unwrap(),
+			lottery_odds: 1.0 / 5000.0,
+			shower: 50,
+		}
+	}
+}
+
+#[derive(Resource, Debug)]
+pub struct Jackpot {
+	/// Kept in fractions of a cent, so small contributions aren't lost to rounding.
+	pool_cents: f64,
+	/// Where the pool is saved between sessions.
+	pub file: Option<PathBuf>,
+	rng: StdRng,
+}
+
+impl Jackpot {
+	/// Picks up the pool from `file` where the last session left it, if there is one.
+	pub fn load(config: &JackpotConfig, file: Option<PathBuf>, seed: u64) -> io::Result<Self> {
+		let pool_cents = match &file {
+			Some(path) if path.exists() => {
+				let saved = fs::read_to_string(path)?;
+				saved.trim().parse().map_err(|_| {
+					io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!("Invalid jackpot `{}`", saved.trim()),
+					)
+				})?
+			}
+			_ => cents(&config.seed) as f64,
+		};
+		Ok(Self {
+			pool_cents,
+			file,
+			rng: StdRng::seed_from_u64(seed),
+		})
+	}
+
+	pub fn pool(&self) -> Currency {
+		from_cents(self.pool_cents.floor() as i64)
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JackpotCause {
+	Pocket,
+	Lottery,
+}
+
+/// Sent when the player wins the pool. It has already been added to [`Winnings`].
+#[derive(Event, Debug, Clone)]
+pub struct JackpotWon {
+	pub amount: Currency,
+	pub cause: JackpotCause,
+}
+
+pub fn feed_jackpot(
+	mut jackpot: ResMut<Jackpot>,
+	dropped: Query<(&Coin, &CoinDropReason), Added<Coin>>,
+	config: Res<MachineConfig>,
+) {
+	let Some(config) = &config.jackpot else {
+		return;
+	};
+	for (coin, reason) in &dropped {
+		if *reason == CoinDropReason::Bonus {
+			continue;
+		}
+		jackpot.pool_cents += cents(&coin.value) as f64 * config.contribution;
+	}
+}
+
+pub fn win_jackpot(
+	mut jackpot: ResMut<Jackpot>,
+	mut hits: EventReader<PocketHit>,
+	mut collected: EventReader<CoinCollected>,
+	mut winnings: ResMut<Winnings>,
+	mut won: EventWriter<JackpotWon>,
+	mut showers: EventWriter<CoinShower>,
+	machine: Res<MachineConfig>,
+) {
+	let Some(config) = &machine.jackpot else {
+		return;
+	};
+	let pocket =
+		hits.read()
+			.filter(|hit| matches!(hit.reward, Reward::Jackpot))
+			.count() > 0;
+	let odds = config.lottery_odds.clamp(0.0, 1.0);
+	let mut lottery = false;
+	for _ in collected.read().filter(|ev| !ev.prefilled) {
+		lottery |= jackpot.rng.gen_bool(odds);
+	}
+	let cause = match (pocket, lottery) {
+		(true, _) => JackpotCause::Pocket,
+		(false, true) => JackpotCause::Lottery,
+		(false, false) => return,
+	};
+	let amount = jackpot.pool();
+	info!(%amount, ?cause, "Jackpot!");
+	winnings.0 = winnings.0.clone() + amount.clone();
+	jackpot.pool_cents = cents(&config.seed) as f64;
+	won.send(JackpotWon { amount, cause });
+	if config.shower > 0 {
+		showers.send(CoinShower::new(config.shower, machine.coin_value.clone()));
+	}
+}
+
+/// Saves the pool every few seconds while it changes, and on exit.
+pub fn save_jackpot(
+	jackpot: Res<Jackpot>,
+	mut exit: EventReader<AppExit>,
+	mut won: EventReader<JackpotWon>,
+	time: Res<Time<Real>>,
+	mut last_save: Local<Duration>,
+	mut unsaved: Local<bool>,
+) {
+	let Some(path) = &jackpot.file else {
+		return;
+	};
+	*unsaved |= jackpot.is_changed();
+	let exiting = exit.read().count() > 0;
+	let won = won.read().count() > 0;
+	let due = time.elapsed() - *last_save >= Duration::from_secs(5);
+	if !(*unsaved && (exiting || won || due)) {
+		return;
+	}
+	*last_save = time.elapsed();
+	*unsaved = false;
+	if let Err(e) = fs::write(path, jackpot.pool_cents.to_string()) {
+		error!(?path, "Failed to save jackpot: {e}");
+	}
+}
@@ -0,0 +1,225 @@
+use crate::coins::{Coin, CoinDropReason};
+use crate::GameState;
+use avian3d::prelude::{Collider, Collisions, Sensor};
+use bevy::prelude::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Counts which lane coins leave the peg board through, and how long they took to
+/// get there. Lanes are spawned by [`crate::machine::spawn_machine`].
+pub struct LanesPlugin;
+
+impl Plugin for LanesPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<LaneHistogram>().add_systems(
+			FixedUpdate,
+			(mark_entries, record_exits)
+				.chain()
+				.run_if(in_state(GameState::Playing)),
+		);
+	}
+}
+
+/// Invisible sensor across part of the bottom of the peg board.
+#[derive(Component, Debug, Clone)]
+#[require(Collider, Sensor)]
+pub struct ExitLane(pub usize);
+
+/// When a dropped coin started falling.
+#[derive(Component, Debug, Clone)]
+pub struct EnteredField(pub f32);
+
+/// A coin that has been counted in the [`LaneHistogram`] already.
+#[derive(Component, Debug, Clone)]
+pub struct ExitedField;
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LaneHistogram {
+	/// Coins through each lane, left to right.
+	pub counts: Vec<u64>,
+	/// Seconds spent in the field by every coin through each lane, added up.
+	pub field_secs: Vec<f32>,
+}
+
+impl LaneHistogram {
+	pub fn total(&self) -> u64 {
+		self.counts.iter().sum()
+	}
+
+	pub fn mean_secs(&self, lane: usize) -> Option<f32> {
+		let count = *self.counts.get(lane)?;
+		(count > 0).then(|| self.field_secs[lane] / count as f32)
+	}
+
+	/// Share of coins each lane would get if every peg sent coins left or right with
+	/// equal odds, i.e. the binomial distribution.
+	pub fn binomial(&self) -> Vec<f64> {
+		if self.counts.is_empty() {
+			return Vec::new();
+		}
+		let n = self.counts.len() - 1;
+		let mut shares = Vec::with_capacity(n + 1);
+		// n choose k, built up from n choose k-1
+		let mut choose = 1.0;
+		for k in 0..=n {
+			if k > 0 {
+				choose *= (n + 1 - k) as f64 / k as f64;
+			}
+			shares.push(choose / 2f64.powi(n as i32));
+		}
+		shares
+	}
+
+	pub fn to_csv(&self) -> String {
+		let total = self.total().max(1) as f64;
+		let mut csv = String::from("lane,coins,share,binomial_share,mean_secs\n");
+		for (lane, expected) in self.binomial().into_iter().enumerate() {
+			let _ = writeln!(
+				csv,
+				"{lane},{},{:.4},{expected:.4},{}",
+				self.counts[lane],
+				self.counts[lane] as f64 / total,
+				self.mean_secs(lane)
+					.map_or(String::new(), |secs| format!("{secs:.3}")),
+			);
+		}
+		csv
+	}
+
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		fs::write(path, self.to_csv())
+	}
+}
+
+/// One bar per lane, scaled to the busiest one, with its share next to the binomial one.
+impl std::fmt::Display for LaneHistogram {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let max = self.counts.iter().copied().max().unwrap_or(0).max(1);
+		let total = self.total().max(1) as f64;
+		for (lane, expected) in self.binomial().into_iter().enumerate() {
+			let count = self.counts[lane];
+			let bar = "#".repeat((20 * count / max) as usize);
+			write!(
+				f,
+				"{lane:>2} {bar:<20} {count:>5} ({:>5.1}% vs {:>5.1}%)",
+				100.0 * count as f64 / total,
+				100.0 * expected,
+			)?;
+			if let Some(secs) = self.mean_secs(lane) {
+				write!(f, " {secs:.1}s")?;
+			}
+			if lane + 1 < self.counts.len() {
+				writeln!(f)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+pub fn mark_entries(
+	mut cmds: Commands,
+	dropped: Query<Entity, (Added<Coin>, With<CoinDropReason>)>,
+	t: Res<Time>,
+) {
+	for id in &dropped {
+		cmds.entity(id).insert(EnteredField(t.elapsed_secs()));
+	}
+}
+
+pub fn record_exits(
+	mut cmds: Commands,
+	mut histogram: ResMut<LaneHistogram>,
+	lanes: Query<(Entity, &ExitLane)>,
+	coins: Query<&EnteredField, (With<Coin>, Without<ExitedField>)>,
+	collisions: Res<Collisions>,
+	t: Res<Time>,
+) {
+	let lane_count = lanes.iter().map(|(_, lane)| lane.0 + 1).max().unwrap_or(0);
+	if histogram.counts.len() < lane_count {
+		histogram.counts.resize(lane_count, 0);
+		histogram.field_secs.resize(lane_count, 0.0);
+	}
+	// Coins straddling two lanes only count in the first
+	let mut exited = Vec::new();
+	for (lane_id, lane) in &lanes {
+		for contacts in collisions.collisions_with_entity(lane_id) {
+			let coin = if contacts.entity1 == lane_id {
+				contacts.entity2
+			} else {
+				contacts.entity1
+			};
+			let Ok(entered) = coins.get(coin) else {
+				continue;
+			};
+			if exited.contains(&coin) {
+				continue;
+			}
+			exited.push(coin);
+			histogram.counts[lane.0] += 1;
+			histogram.field_secs[lane.0] += t.elapsed_secs() - entered.0;
+			cmds.entity(coin).insert(ExitedField);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn histogram(counts: &[u64]) -> LaneHistogram {
+		LaneHistogram {
+			counts: counts.to_vec(),
+			field_secs: vec![0.0; counts.len()],
+		}
+	}
+
+	#[test]
+	fn binomial_follows_pascals_triangle() {
+		let shares = histogram(&[0; 5]).binomial();
+		assert_eq!(shares, [1.0, 4.0, 6.0, 4.0, 1.0].map(|n| n / 16.0));
+	}
+
+	#[test]
+	fn binomial_shares_add_up_to_one() {
+		for lanes in 1..20 {
+			let total = histogram(&vec![0; lanes]).binomial().iter().sum::<f64>();
+			assert!((total - 1.0).abs() < 1e-9, "{lanes} lanes");
+		}
+	}
+
+	#[test]
+	fn no_lanes_have_no_shares() {
+		assert!(histogram(&[]).binomial().is_empty());
+		assert_eq!(
+			histogram(&[]).to_csv(),
+			"lane,coins,share,binomial_share,mean_secs\n"
+		);
+	}
+
+	#[test]
+	fn mean_secs_skips_empty_lanes() {
+		let histogram = LaneHistogram {
+			counts: vec![0, 4],
+			field_secs: vec![0.0, 10.0],
+		};
+		assert_eq!(histogram.mean_secs(0), None);
+		assert_eq!(histogram.mean_secs(1), Some(2.5));
+		assert_eq!(histogram.mean_secs(2), None);
+	}
+
+	#[test]
+	fn csv_compares_each_lane_with_the_binomial() {
+		let histogram = LaneHistogram {
+			counts: vec![1, 3],
+			field_secs: vec![2.0, 3.0],
+		};
+		assert_eq!(
+			histogram.to_csv(),
+			"lane,coins,share,binomial_share,mean_secs\n\
+			0,1,0.2500,0.5000,2.000\n\
+			1,3,0.7500,0.5000,1.000\n"
+		);
+	}
+}
@@ -1,27 +1,288 @@
-use crate::coins::Coin;
-use crate::Winnings;
-use avian3d::collision::{Collider, Sensor};
+use crate::coins::{ActiveChute, Coin, CoinQueue};
+use crate::combos::{Combo, ComboConfig};
+use crate::hinges::{hinge_rotation, Hinge, HingeConfig, HingeKind};
+use crate::jackpot::JackpotConfig;
+use crate::lanes::ExitLane;
+use crate::nudge::NudgeConfig;
+use crate::obstacles::{Obstacle, ObstacleConfig, ObstacleShape, OBSTACLE_DEPTH};
+use crate::pegs::{PegPattern, PEG_FIELD, PEG_RADIUS};
+use crate::pockets::{BonusPocket, CollectionMultiplier, PocketConfig, PocketPlace, Reward};
+use crate::power_ups::{PowerUpConfig, PowerUpKind};
+use crate::prefill::Prefilled;
+use crate::prizes::{Prize, PrizeCollected, PrizeConfig, PrizeKind};
+use crate::pusher::{resisting_force, ForceLimit, PusherJammed, PusherMotion};
+use crate::reels::ReelConfig;
+use crate::special::{CollectionBoost, SpecialCoinConfig, SpecialKind};
+use crate::{cents, from_cents, GameState, Winnings};
+use avian3d::collision::{Collider, ColliderAabb, Collisions, Sensor};
 use avian3d::math::FRAC_PI_2;
-use avian3d::prelude::{CoefficientCombine, Friction, LinearVelocity, Restitution, RigidBody};
-use bevy::prelude::EaseFunction::SineInOut;
+use avian3d::prelude::{
+	AngularVelocity, CoefficientCombine, Friction, Joint, LinearVelocity, Restitution,
+	RevoluteJoint, RigidBody,
+};
 use bevy::prelude::*;
-use std::f32::consts::FRAC_PI_8;
-use std::sync::Arc;
+use currency::Currency;
+use std::f32::consts::{FRAC_PI_8, PI};
+use std::time::Duration;
 
 pub struct MachinePlugin;
 
 impl Plugin for MachinePlugin {
 	fn build(&self, app: &mut App) {
-		app.add_systems(Startup, spawn_machine)
-			.add_systems(FixedUpdate, move_piston)
-			.add_systems(Update, collect);
+		app.init_resource::<MachineConfig>()
+			.add_event::<EmptyTray>()
+			.add_event::<CoinCollected>()
+			.add_event::<PusherJammed>()
+			.add_systems(Startup, spawn_machine)
+			.add_systems(
+				FixedUpdate,
+				move_piston.run_if(in_state(GameState::Playing)),
+			)
+			.add_systems(
+				Update,
+				(
+					collect.run_if(in_state(GameState::Playing)),
+					discard_spills.run_if(in_state(GameState::Settling)),
+					empty_tray,
+				)
+					.chain(),
+			);
+	}
+}
+
+/// Layout of the machine spawned by [`spawn_machine`].
+#[derive(Resource, Debug, Clone)]
+pub struct MachineConfig {
+	pub chutes: Vec<ChuteConfig>,
+	/// Index into `chutes` that manual drops use until the player picks another one.
+	pub default_chute: usize,
+	/// What the player's coins are worth. Free coins from bonuses are always $1.
+	pub coin_value: Currency,
+	/// Shortest interval the auto-drop timer can be set to.
+	pub min_auto_interval: Duration,
+	/// Show queued coins in a hopper at the top of the cabinet.
+	pub hopper: bool,
+	pub pegs: PegPattern,
+	/// How many [`ExitLane`]s to split the bottom of the peg board into.
+	pub exit_lanes: usize,
+	/// Flippers and gates on the peg board.
+	pub hinges: Vec<HingeConfig>,
+	/// Moving things on the peg board.
+	pub obstacles: Vec<ObstacleConfig>,
+	pub pockets: Vec<PocketConfig>,
+	pub jackpot: Option<JackpotConfig>,
+	pub reels: Option<ReelConfig>,
+	pub prizes: Vec<PrizeConfig>,
+	/// Odds of each dropped coin being special, see [`SpecialCoinConfig::roll`].
+	pub special_coins: Vec<SpecialCoinConfig>,
+	pub combo: Option<ComboConfig>,
+	/// `None` to disable nudging altogether.
+	pub nudge: Option<NudgeConfig>,
+	/// What the player can buy, see [`crate::power_ups::BuyPowerUp`].
+	pub power_ups: Vec<PowerUpConfig>,
+	/// Catch collected coins in a tray below the front edge instead of despawning them right away.
+	pub payout_tray: Option<PayoutTrayConfig>,
+	pub pusher: PusherMotion,
+	/// See [`Piston::speed`].
+	pub piston_speed: f32,
+	/// Drive the piston with a motor that can only push so hard, instead of moving it
+	/// through anything in its way.
+	pub pusher_force_limit: Option<ForceLimit>,
+	pub floor_friction: f32,
+	pub platform_friction: f32,
+	/// Bounciness of the floor and walls.
+	pub restitution: f32,
+}
+
+impl Default for MachineConfig {
+	fn default() -> Self {
+		Self {
+			chutes: vec![
+				ChuteConfig {
+					name: "Left".into(),
+					x: -6.5,
+					width: 7.0,
+					kind: ChuteKind::Plinko,
+				},
+				ChuteConfig {
+					name: "Center".into(),
+					x: 0.0,
+					width: 7.0,
+					kind: ChuteKind::Plinko,
+				},
+				ChuteConfig {
+					name: "Right".into(),
+					x: 6.5,
+					width: 7.0,
+					kind: ChuteKind::Plinko,
+				},
+				ChuteConfig {
+					name: "Direct".into(),
+					x: 0.0,
+					width: 12.0,
+					kind: ChuteKind::Bypass,
+				},
+			],
+			default_chute: 1,
+			coin_value: Currency::from_str("$1.00").unwrap(),
+			min_auto_interval: Duration::from_millis(500),
+			hopper: true,
+			pegs: PegPattern::default(),
+			exit_lanes: 8,
+			hinges: vec![
+				// Catch coins from the side chutes and let them through one at a time
+				HingeConfig::gate(Vec2::new(-9.5, 17.0), 5.0, 0.0),
+				HingeConfig::gate(Vec2::new(9.5, 17.0), 5.0, PI),
+				// Below the pegs, pointing down toward the middle. High and short enough that
+				// they never dip into the sliding platform at rest, and flipping up stops a
+				// coin's width short of the lowest pegs.
+				HingeConfig::flipper(Vec2::new(-8.5, -13.0), 3.0, -0.5, 0.3),
+				HingeConfig::flipper(Vec2::new(8.5, -13.0), 3.0, PI + 0.5, PI - 0.3),
+			],
+			obstacles: vec![
+				// Under the center chute, above the pegs
+				ObstacleConfig::spinning(
+					ObstacleShape::Windmill {
+						blades: 4,
+						length: 1.5,
+					},
+					Vec2::new(0.0, 16.5),
+					4.0,
+				),
+				// Between the pegs and the flippers, a coin's width below the lowest pegs and
+				// stopping short of where the flippers flip up to
+				ObstacleConfig::sliding(
+					ObstacleShape::Bar { length: 3.0 },
+					Vec2::new(-3.5, -12.5),
+					Vec2::new(3.5, -12.5),
+					3.0,
+					0.5,
+				),
+			],
+			// Just above the exit lanes, where coins leave the peg board
+			pockets: vec![
+				PocketConfig {
+					name: "x2".into(),
+					place: PocketPlace::Board(Vec2::new(0.0, BOARD_EXIT + 0.5)),
+					size: Vec3::new(2.5, 0.5, 0.5),
+					reward: Reward::Multiplier {
+						factor: 2.0,
+						collections: 10,
+					},
+				},
+				PocketConfig {
+					name: "$5".into(),
+					place: PocketPlace::Board(Vec2::new(-8.75, BOARD_EXIT + 0.5)),
+					size: Vec3::new(2.5, 0.5, 0.5),
+					reward: Reward::Credit(Currency::from_str("$5.00").unwrap()),
+				},
+				PocketConfig {
+					name: "+3 coins".into(),
+					place: PocketPlace::Board(Vec2::new(8.75, BOARD_EXIT + 0.5)),
+					size: Vec3::new(2.5, 0.5, 0.5),
+					reward: Reward::ExtraCoins(3),
+				},
+				// Small, and hidden among the pegs
+				PocketConfig {
+					name: "JACKPOT".into(),
+					place: PocketPlace::Board(Vec2::new(1.0, 12.0)),
+					size: Vec3::new(1.0, 0.5, 1.0),
+					reward: Reward::Jackpot,
+				},
+				// Between the two lowest rows of pegs
+				PocketConfig {
+					name: "CHECK".into(),
+					place: PocketPlace::Board(Vec2::new(-1.0, -8.0)),
+					size: Vec3::new(1.5, 0.5, 1.0),
+					reward: Reward::SpinReels,
+				},
+			],
+			jackpot: Some(JackpotConfig::default()),
+			reels: Some(ReelConfig::default()),
+			prizes: vec![
+				PrizeConfig {
+					kind: PrizeKind::Token,
+					value: Currency::from_str("$5.00").unwrap(),
+					position: Vec3::new(-5.0, -8.0, 6.0),
+				},
+				PrizeConfig {
+					kind: PrizeKind::Box,
+					value: Currency::from_str("$10.00").unwrap(),
+					position: Vec3::new(2.0, -5.0, 6.0),
+				},
+				PrizeConfig {
+					kind: PrizeKind::Ball,
+					value: Currency::from_str("$3.00").unwrap(),
+					position: Vec3::new(6.0, -12.0, 6.0),
+				},
+			],
+			special_coins: vec![
+				SpecialCoinConfig {
+					kind: SpecialKind::Golden { factor: 10 },
+					odds: 0.01,
+				},
+				SpecialCoinConfig {
+					kind: SpecialKind::Multiplier {
+						factor: 2.0,
+						secs: 5.0,
+					},
+					odds: 0.02,
+				},
+			],
+			combo: Some(ComboConfig::default()),
+			nudge: Some(NudgeConfig::default()),
+			power_ups: vec![
+				PowerUpConfig::new(PowerUpKind::FastPiston, "$5.00", 20.0),
+				PowerUpConfig::new(PowerUpKind::Magnet, "$10.00", 10.0),
+				PowerUpConfig::new(PowerUpKind::DoubleValue, "$15.00", 15.0),
+			],
+			payout_tray: Some(PayoutTrayConfig { capacity: 150 }),
+			pusher: Self::pusher_with_stroke(10.0),
+			piston_speed: 1.0,
+			pusher_force_limit: None,
+			floor_friction: 0.5,
+			platform_friction: 1.0,
+			restitution: 0.8,
+		}
+	}
+}
+
+impl MachineConfig {
+	/// The usual sliding platform, moving `stroke` forward from fully retracted.
+	pub fn pusher_with_stroke(stroke: f32) -> PusherMotion {
+		let retracted = Vec3::new(0.0, 15.0, 5.0);
+		PusherMotion::linear(retracted, retracted - Vec3::Y * stroke, 10.0, 0.0)
 	}
 }
 
+#[derive(Debug, Clone)]
+pub struct PayoutTrayConfig {
+	/// The tray empties itself once it holds this many coins.
+	pub capacity: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChuteConfig {
+	pub name: String,
+	/// Horizontal center of the chute.
+	pub x: f32,
+	pub width: f32,
+	pub kind: ChuteKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChuteKind {
+	/// Drops coins at the top of the peg board.
+	Plinko,
+	/// Drops coins straight onto the sliding platform, skipping the pegs.
+	Bypass,
+}
+
 pub fn spawn_machine(
 	mut cmds: Commands,
 	mut meshes: ResMut<Assets<Mesh>>,
 	mut mats: ResMut<Assets<StandardMaterial>>,
+	config: Res<MachineConfig>,
 ) {
 	let floor_collider = Collider::cuboid(20.0, 40.0, 5.0);
 	let floor_mesh = meshes.add(Cuboid::new(20.0, 40.0, 5.0));
@@ -33,13 +294,14 @@ pub fn spawn_machine(
 	let walls_and_floor_shared_bundle = (
 		RigidBody::Static,
 		MeshMaterial3d(machine_mat.clone()),
-		Restitution::new(0.8),
+		Restitution::new(config.restitution),
 	);
 	// Floor
 	cmds.spawn((
 		walls_and_floor_shared_bundle.clone(),
 		floor_collider.clone(),
 		Mesh3d(floor_mesh.clone()),
+		Friction::new(config.floor_friction),
 	));
 	let walls_collider = Collider::cuboid(50.0, 60.0, 5.0);
 	let walls_mesh = meshes.add(Cuboid::new(50.0, 60.0, 5.0));
@@ -68,152 +330,513 @@ pub fn spawn_machine(
 	));
 
 	// Rear
-	cmds.spawn((
-		RigidBody::Static,
-		Collider::cuboid(20.0, 5.0, 45.0),
-		Mesh3d(meshes.add(Cuboid::new(20.0, 5.0, 45.0))),
-		MeshMaterial3d(mats.add(StandardMaterial {
-			base_color: Color::linear_rgb(0.02, 0.004, 0.03),
-			reflectance: 0.01,
-			..default()
-		})),
-		Transform {
-			translation: Vec3::new(0.0, 15.0, 22.5),
-			rotation: Quat::from_rotation_x(-FRAC_PI_8 * 0.5),
-			..default()
-		},
-		Friction {
-			dynamic_coefficient: 0.2,
-			static_coefficient: 0.0,
-			combine_rule: CoefficientCombine::Max,
-		},
-		Restitution::new(0.0).with_combine_rule(CoefficientCombine::Min),
-	))
-	.with_children(|cmds| {
-		let peg_collider = Collider::cylinder(0.25, 0.5);
-		let peg_mesh = meshes.add(Cylinder::new(0.25, 0.5));
-		let peg_bundle = (
+	let rear_xform = Transform {
+		translation: Vec3::new(0.0, 15.0, 22.5),
+		rotation: Quat::from_rotation_x(-FRAC_PI_8 * 0.5),
+		..default()
+	};
+	let rear = cmds
+		.spawn((
 			RigidBody::Static,
-			peg_collider,
-			Mesh3d(peg_mesh.clone()),
-			MeshMaterial3d(machine_mat.clone()),
-		);
-		for v in 0..7 {
-			for h in 0..5 {
-				let h_offset = 2.0 * (v % 2) as f32;
+			Collider::cuboid(20.0, 5.0, 45.0),
+			Mesh3d(meshes.add(Cuboid::new(20.0, 5.0, 45.0))),
+			MeshMaterial3d(mats.add(StandardMaterial {
+				base_color: Color::linear_rgb(0.02, 0.004, 0.03),
+				reflectance: 0.01,
+				..default()
+			})),
+			rear_xform,
+			Friction {
+				dynamic_coefficient: 0.2,
+				static_coefficient: 0.0,
+				combine_rule: CoefficientCombine::Max,
+			},
+			Restitution::new(0.0).with_combine_rule(CoefficientCombine::Min),
+		))
+		.with_children(|cmds| {
+			let peg_collider = Collider::cylinder(PEG_RADIUS, 0.5);
+			let peg_mesh = meshes.add(Cylinder::new(PEG_RADIUS, 0.5));
+			let peg_bundle = (
+				RigidBody::Static,
+				peg_collider,
+				Mesh3d(peg_mesh.clone()),
+				MeshMaterial3d(machine_mat.clone()),
+			);
+			let mut layout = config.pegs.generate(PEG_FIELD);
+			if let Err(e) = layout.validate() {
+				error!("Coins would get stuck in this peg layout, leaving the pegs out: {e}");
+				layout.pegs.clear();
+			}
+			for peg in layout.pegs {
 				cmds.spawn((
 					peg_bundle.clone(),
 					Transform {
-						translation: Vec3::new(
-							(h as f32 * 4.0) - 9.0 + h_offset,
-							-2.75,
-							(v as f32 * 4.0) - 10.0,
-						),
+						translation: Vec3::new(peg.x, -2.75, peg.y),
 						..default()
 					},
 					Friction::new(0.0).with_combine_rule(CoefficientCombine::Min),
 					Restitution::new(0.9),
 				));
 			}
-		}
 
-		// Glass in front of pegs to prevent coins escaping plinko
+			// Just above the platform, so they count where coins really leave the field
+			let lane_width = 20.0 / config.exit_lanes.max(1) as f32;
+			for i in 0..config.exit_lanes {
+				cmds.spawn((
+					ExitLane(i),
+					Collider::cuboid(lane_width, 0.5, 0.5),
+					Transform::from_translation(Vec3::new(
+						-10.0 + (i as f32 + 0.5) * lane_width,
+						OBSTACLE_DEPTH,
+						BOARD_EXIT,
+					)),
+				));
+			}
+
+			for (i, pocket) in config.pockets.iter().enumerate() {
+				let PocketPlace::Board(at) = pocket.place else {
+					continue;
+				};
+				cmds.spawn((
+					BonusPocket {
+						index: i,
+						reward: pocket.reward.clone(),
+					},
+					Name::new(pocket.name.clone()),
+					Collider::cuboid(pocket.size.x, pocket.size.y, pocket.size.z),
+					Transform::from_translation(Vec3::new(at.x, OBSTACLE_DEPTH, at.y)),
+				));
+			}
+
+			// Glass in front of pegs to prevent coins escaping plinko
+			cmds.spawn((
+				RigidBody::Static,
+				Collider::cuboid(20.0, 1.0, 35.0),
+				Transform {
+					translation: Vec3::new(0.0, -3.375, 5.0),
+					..default()
+				},
+				Friction::new(0.0).with_combine_rule(CoefficientCombine::Min),
+			));
+
+			for (i, chute) in config.chutes.iter().enumerate() {
+				if chute.kind != ChuteKind::Plinko {
+					continue;
+				}
+				cmds.spawn((
+					DropZone(i),
+					Name::new(chute.name.clone()),
+					Collider::cuboid(chute.width, 0.5, 2.0),
+					Transform {
+						translation: Vec3::new(chute.x, -2.625, 20.0),
+						..default()
+					},
+				));
+			}
+		})
+		.id();
+
+	// Shared by everything on the board that moves
+	let moving_mat = mats.add(StandardMaterial {
+		base_color: Color::linear_rgb(0.6, 0.05, 0.05),
+		metallic: 1.0,
+		..default()
+	});
+	for (i, hinge) in config.hinges.iter().enumerate() {
+		let pivot = Vec3::new(hinge.pivot.x, OBSTACLE_DEPTH, hinge.pivot.y);
+		let rotation = hinge_rotation(hinge.rest_angle);
+		let center = pivot + rotation * Vec3::X * 0.5 * hinge.length;
+		let id = cmds
+			.spawn((
+				Hinge::new(i, rear_xform.rotation, hinge),
+				Name::new(match hinge.kind {
+					HingeKind::Flipper { .. } => "Flipper",
+					HingeKind::Gate => "Gate",
+				}),
+				Collider::cuboid(hinge.length, 0.3, 0.4),
+				Mesh3d(meshes.add(Cuboid::new(hinge.length, 0.3, 0.4))),
+				MeshMaterial3d(moving_mat.clone()),
+				Transform {
+					translation: rear_xform.transform_point(center),
+					rotation: rear_xform.rotation * rotation,
+					..default()
+				},
+				Friction::new(0.0).with_combine_rule(CoefficientCombine::Min),
+				Restitution::new(0.5),
+			))
+			.id();
+		cmds.spawn(
+			RevoluteJoint::new(rear, id)
+				.with_local_anchor_1(pivot)
+				.with_local_anchor_2(Vec3::NEG_X * 0.5 * hinge.length)
+				.with_aligned_axis(Vec3::Y),
+		);
+	}
+
+	for obstacle in &config.obstacles {
+		let (translation, rotation) = obstacle.motion.sample(0.0).unwrap_or_default();
 		cmds.spawn((
-			RigidBody::Static,
-			Collider::cuboid(20.0, 1.0, 35.0),
+			Obstacle {
+				motion: obstacle.motion.clone(),
+				board: rear_xform,
+				progress: 0.0,
+			},
+			obstacle.shape.collider(),
+			Mesh3d(meshes.add(obstacle.shape.mesh())),
+			MeshMaterial3d(moving_mat.clone()),
 			Transform {
-				translation: Vec3::new(0.0, -3.375, 5.0),
+				translation: rear_xform.transform_point(translation),
+				rotation: rear_xform.rotation * rotation,
 				..default()
 			},
-			Friction::new(0.0).with_combine_rule(CoefficientCombine::Min),
+			Friction::new(0.3),
+			Restitution::new(0.5),
 		));
+	}
 
+	for (i, pocket) in config.pockets.iter().enumerate() {
+		let PocketPlace::World(at) = pocket.place else {
+			continue;
+		};
 		cmds.spawn((
-			DropZone,
-			Collider::cuboid(20.0, 0.5, 2.0),
-			Sensor,
+			BonusPocket {
+				index: i,
+				reward: pocket.reward.clone(),
+			},
+			Name::new(pocket.name.clone()),
+			Collider::cuboid(pocket.size.x, pocket.size.y, pocket.size.z),
+			Transform::from_translation(at),
+		));
+	}
+
+	for (i, chute) in config.chutes.iter().enumerate() {
+		if chute.kind != ChuteKind::Bypass {
+			continue;
+		}
+		// Between the glass and the front of the platform's stroke
+		cmds.spawn((
+			DropZone(i),
+			Name::new(chute.name.clone()),
+			Collider::cuboid(chute.width, 0.5, 2.0),
 			Transform {
-				translation: Vec3::new(0.0, -2.625, 20.0),
+				translation: Vec3::new(chute.x, 6.0, 20.0),
 				..default()
 			},
 		));
-	});
+	}
+	cmds.insert_resource(ActiveChute(config.default_chute));
+
+	if let Some(tray) = &config.payout_tray {
+		let tray_bundle = MeshMaterial3d(machine_mat.clone());
+		cmds.spawn((
+			PayoutTray {
+				capacity: tray.capacity,
+			},
+			RigidBody::Static,
+			Transform::from_translation(Vec3::new(0.0, -26.0, -14.0)),
+		))
+		.with_children(|cmds| {
+			// Bottom
+			cmds.spawn((
+				tray_bundle.clone(),
+				Collider::cuboid(24.0, 16.0, 1.0),
+				Mesh3d(meshes.add(Cuboid::new(24.0, 16.0, 1.0))),
+			));
+			let side_wall = (
+				tray_bundle.clone(),
+				Collider::cuboid(1.0, 16.0, 6.0),
+				Mesh3d(meshes.add(Cuboid::new(1.0, 16.0, 6.0))),
+			);
+			let end_wall = (
+				tray_bundle.clone(),
+				Collider::cuboid(24.0, 1.0, 6.0),
+				Mesh3d(meshes.add(Cuboid::new(24.0, 1.0, 6.0))),
+			);
+			for x in [-12.5, 12.5] {
+				cmds.spawn((
+					side_wall.clone(),
+					Transform::from_translation(Vec3::new(x, 0.0, 3.5)),
+				));
+			}
+			for y in [-8.5, 8.5] {
+				cmds.spawn((
+					end_wall.clone(),
+					Transform::from_translation(Vec3::new(0.0, y, 3.5)),
+				));
+			}
+			// Coins are paid out as soon as they touch this
+			cmds.spawn((
+				TraySensor,
+				Collider::cuboid(24.0, 16.0, 6.0),
+				Transform::from_translation(Vec3::new(0.0, 0.0, 3.5)),
+			));
+		});
+	}
 
 	// Sliding platform
-	cmds.spawn((
+	let (translation, rotation) = config.pusher.sample(0.0).unwrap_or_default();
+	let mut piston = cmds.spawn((
 		RigidBody::Kinematic,
 		Collider::cuboid(20.0, 30.0, 5.0),
 		Mesh3d(meshes.add(Cuboid::new(20.0, 30.0, 5.0))),
 		MeshMaterial3d(machine_mat.clone()),
 		Transform {
-			translation: Vec3::new(0.0, 10.0, 5.0),
+			translation,
+			rotation,
 			..default()
 		},
 		Piston {
-			curve: Arc::new(
-				EasingCurve::new(
-					Vec3::new(0.0, 15.0, 5.0),
-					Vec3::new(0.0, 5.0, 5.0),
-					SineInOut,
-				)
-				.ping_pong()
-				.unwrap()
-				.forever()
-				.unwrap(),
-			),
-			speed: 0.1,
+			motion: config.pusher.clone(),
+			speed: config.piston_speed,
+			boost: 1.0,
+			progress: 0.0,
 		},
-		Friction::new(1.0),
+		Friction::new(config.platform_friction),
 	));
+	if let Some(limit) = &config.pusher_force_limit {
+		piston.insert(limit.clone());
+	}
 }
 
+/// How far up from the center of the peg board coins leave it. The top of the sliding
+/// platform cuts across the board a little below this, at about -15.8, so nothing that
+/// coins should reach can go any lower.
+pub const BOARD_EXIT: f32 = -15.0;
+
+/// Where coins are spawned. The index is the chute's position in [`MachineConfig::chutes`].
 #[derive(Component, Clone, Debug)]
-#[require(Collider, Sensor)]
-pub struct DropZone;
+#[require(Collider, Sensor, CoinQueue)]
+pub struct DropZone(pub usize);
 
 #[derive(Component, Clone)]
 #[require(RigidBody(|| RigidBody::Kinematic), Collider)]
 pub struct Piston {
-	pub curve: Arc<dyn Curve<Vec3> + Send + Sync + 'static>,
+	pub motion: PusherMotion,
+	/// Playback rate of `motion`.
 	pub speed: f32,
+	/// Multiplies `speed`, set by [`crate::power_ups::PowerUpKind::FastPiston`].
+	pub boost: f32,
+	/// How far into `motion` the piston is. Advanced by `speed` each second it spends
+	/// moving, so pausing it or changing its speed doesn't make it jump.
+	pub progress: f32,
+}
+
+impl Piston {
+	/// How far through its cycle the piston is, from 0.0 up to (not including) 1.0.
+	pub fn phase(&self) -> f32 {
+		(self.progress / self.motion.period()).fract()
+	}
 }
 
 pub fn move_piston(
-	mut q: Query<(&mut Transform, &mut LinearVelocity, &Piston)>,
+	mut q: Query<(
+		Entity,
+		&mut Transform,
+		&mut LinearVelocity,
+		&mut AngularVelocity,
+		&mut Piston,
+		Option<&mut ForceLimit>,
+		&ColliderAabb,
+	)>,
+	coins: Query<&GlobalTransform, With<Coin>>,
+	collisions: Res<Collisions>,
+	mut jammed: EventWriter<PusherJammed>,
 	t: Res<Time<Fixed>>,
 ) {
-	let dt = t.timestep();
-	for (mut xform, mut vel, piston) in q.iter_mut() {
-		let Some((a, b)) = piston.curve.sample(t.elapsed_secs() * piston.speed).zip(
-			piston
-				.curve
-				.sample((t.elapsed() + dt).as_secs_f32() * piston.speed),
-		) else {
+	let dt = t.timestep().as_secs_f32();
+	for (id, mut xform, mut vel, mut ang_vel, mut piston, limit, aabb) in q.iter_mut() {
+		let direction = match limit {
+			Some(mut limit) => {
+				// Backing off or waiting to retry doesn't care how hard the coins push
+				let force = if limit.is_jammed() {
+					0.0
+				} else {
+					resisting_force(id, aabb, &coins, &collisions, dt)
+				};
+				let (direction, just_jammed) = limit.update(force, dt);
+				if just_jammed {
+					warn!(?id, force, "Pusher jammed");
+					jammed.send(PusherJammed { piston: id, force });
+				}
+				direction
+			}
+			None => 1.0,
+		};
+		let next = piston.progress + dt * piston.speed * piston.boost * direction;
+		let Some(step) = piston
+			.motion
+			.step(&Transform::IDENTITY, piston.progress, next, dt)
+		else {
 			error!(
-				t = t.elapsed_secs(),
+				t = piston.progress,
 				speed = piston.speed,
-				"Failed to sample curve"
+				"Failed to sample pusher motion"
 			);
 			return;
 		};
 		// Prevents drift from floating point imprecision
-		xform.translation = a;
-		// Velocity is needed, not just position, for friction to move coins.
-		vel.0 = (b - a) / dt.as_secs_f32();
+		xform.translation = step.translation;
+		xform.rotation = step.rotation;
+		vel.0 = step.linear_velocity;
+		ang_vel.0 = step.angular_velocity;
+		piston.progress = next;
 	}
 }
 
+#[derive(Component, Debug, Clone)]
+#[require(Transform, Visibility)]
+pub struct PayoutTray {
+	pub capacity: usize,
+}
+
+#[derive(Component, Debug, Clone)]
+#[require(Collider, Sensor)]
+pub struct TraySensor;
+
+/// A coin that has already been paid out and is sitting in the [`PayoutTray`].
+#[derive(Component, Debug, Clone)]
+pub struct InTray;
+
+/// Sent whenever a coin is paid out.
+#[derive(Event, Debug, Clone)]
+pub struct CoinCollected {
+	pub coin: Coin,
+	/// What was added to [`Winnings`] for it, which can be more than its value.
+	pub paid: Currency,
+	/// Pre-filled coins that haven't moved since the bed settled.
+	pub prefilled: bool,
+	pub special: Option<SpecialKind>,
+}
+
+/// Clears every coin out of the [`PayoutTray`]. They have already been paid out.
+#[derive(Event, Debug, Clone, Default)]
+pub struct EmptyTray;
+
 pub fn collect(
 	mut cmds: Commands,
-	coins: Query<(Entity, &GlobalTransform, &Coin)>,
+	coins: Query<
+		(
+			Entity,
+			&GlobalTransform,
+			&Coin,
+			Option<&SpecialKind>,
+			Has<Prefilled>,
+		),
+		Without<InTray>,
+	>,
+	tray_coins: Query<(Entity, &GlobalTransform), With<InTray>>,
+	prizes: Query<(Entity, &GlobalTransform, &Prize)>,
+	tray_sensor: Option<Single<Entity, With<TraySensor>>>,
+	collisions: Res<Collisions>,
 	mut winnings: ResMut<Winnings>,
+	mut multiplier: ResMut<CollectionMultiplier>,
+	mut boost: ResMut<CollectionBoost>,
+	mut combo: ResMut<Combo>,
+	config: Res<MachineConfig>,
+	mut collected: EventWriter<CoinCollected>,
+	mut prizes_collected: EventWriter<PrizeCollected>,
 ) {
-	for (id, xform, coin) in coins.iter() {
+	// Prizes don't stay in the tray, they go straight into the inventory
+	let mut won_prizes = Vec::new();
+	let mut pay = |coin: &Coin, special: Option<&SpecialKind>, prefilled: bool| {
+		info!("Collecting {coin:?}");
+		let value = match special.copied() {
+			Some(SpecialKind::Golden { factor }) => from_cents(cents(&coin.value) * factor as i64),
+			Some(SpecialKind::Multiplier { factor, secs }) => {
+				boost.start(factor, secs);
+				coin.value.clone()
+			}
+			None => coin.value.clone(),
+		};
+		let mut paid = boost.apply(&multiplier.apply(&value));
+		if let Some(combo_config) = &config.combo {
+			let factor = combo.hit(combo_config);
+			paid = from_cents((cents(&paid) as f32 * factor).round() as i64);
+		}
+		winnings.0 = winnings.0.clone() + paid.clone();
+		info!("Score: {}", winnings.0);
+		collected.send(CoinCollected {
+			coin: coin.clone(),
+			paid,
+			prefilled,
+			special: special.copied(),
+		});
+	};
+
+	if let Some(sensor) = tray_sensor {
+		for col in collisions.collisions_with_entity(*sensor) {
+			let other = if col.entity1 == *sensor {
+				col.entity2
+			} else {
+				col.entity1
+			};
+			if prizes.contains(other) && !won_prizes.contains(&other) {
+				won_prizes.push(other);
+			}
+			let Ok((id, _, coin, special, prefilled)) = coins.get(other) else {
+				continue;
+			};
+			cmds.entity(id).insert(InTray);
+			pay(coin, special, prefilled);
+		}
+	}
+	for (id, xform, coin, special, prefilled) in coins.iter() {
+		// Missed the tray, or there isn't one
 		if xform.translation().z < -20.0 {
-			info!("Collecting {coin:?}");
 			cmds.entity(id).despawn_recursive();
-			winnings.0 = winnings.0.clone() + coin.value.clone();
-			info!("Score: {}", winnings.0);
+			pay(coin, special, prefilled);
+		}
+	}
+	for (id, xform, _) in prizes.iter() {
+		if xform.translation().z < -20.0 && !won_prizes.contains(&id) {
+			won_prizes.push(id);
 		}
 	}
+	for id in won_prizes {
+		let Ok((_, _, prize)) = prizes.get(id) else {
+			continue;
+		};
+		cmds.entity(id).despawn_recursive();
+		prizes_collected.send(PrizeCollected {
+			kind: prize.kind,
+			value: prize.value.clone(),
+		});
+	}
+	for (id, xform) in tray_coins.iter() {
+		if xform.translation().z < -20.0 {
+			warn!(?id, "Coin escaped the payout tray");
+			cmds.entity(id).despawn_recursive();
+		}
+	}
+}
+
+/// Despawns coins that fall off the front while the bed is settling, without paying for
+/// them. The player hasn't had a chance to win anything yet.
+pub fn discard_spills(mut cmds: Commands, coins: Query<(Entity, &GlobalTransform), With<Coin>>) {
+	for (id, xform) in &coins {
+		// Below the top of the floor
+		if xform.translation().z < 0.0 {
+			cmds.entity(id).despawn_recursive();
+		}
+	}
+}
+
+pub fn empty_tray(
+	mut cmds: Commands,
+	mut events: EventReader<EmptyTray>,
+	tray: Option<Single<&PayoutTray>>,
+	tray_coins: Query<Entity, With<InTray>>,
+) {
+	let requested = events.read().count() > 0;
+	let full = tray.is_some_and(|tray| tray_coins.iter().len() >= tray.capacity);
+	if !(requested || full) {
+		return;
+	}
+	info!(
+		count = tray_coins.iter().len(),
+		full, "Emptying payout tray"
+	);
+	for id in &tray_coins {
+		cmds.entity(id).despawn_recursive();
+	}
 }
@@ -5,26 +5,169 @@ use bevy::core_pipeline::experimental::taa::TemporalAntiAliasPlugin;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 use currency::Currency;
+use num::ToPrimitive;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::SystemTime;
 
+pub mod auto_drop;
 pub mod cam;
+pub mod cli;
 pub mod coins;
+pub mod combos;
 pub mod env;
+pub mod headless;
+pub mod hinges;
+pub mod hopper;
+pub mod house_edge;
+pub mod jackpot;
+pub mod lanes;
 pub mod machine;
+pub mod nudge;
+pub mod obstacles;
+pub mod offline;
+pub mod pegs;
+pub mod pockets;
+pub mod power_ups;
+pub mod prefill;
+pub mod prizes;
+pub mod pusher;
+pub mod reels;
+pub mod replay;
+pub mod special;
+pub mod stats;
+pub mod strategy;
+pub mod sweep;
 pub mod tools;
 pub mod ui;
+pub mod upgrades;
 
 fn main() {
-	App::new()
-		.add_plugins(DefaultPlugins)
-		.add_plugins((FrameTimeDiagnosticsPlugin, TemporalAntiAliasPlugin))
-		.add_plugins(PhysicsPlugins::default())
+	let mut options = match cli::Options::from_args() {
+		Ok(options) => options,
+		Err(e) => {
+			eprintln!("{e}");
+			std::process::exit(2);
+		}
+	};
+
+	if !options.sweep.is_empty() {
+		if let Err(e) = sweep::run(&options) {
+			eprintln!("Sweep failed: {e}");
+			std::process::exit(1);
+		}
+		return;
+	}
+	if !options.headless && options.record.is_none() && options.replay.is_none() {
+		// Analysis runs shouldn't touch the jackpot players are building up, and replays
+		// have to start from the same machine every time
+		options
+			.jackpot_file
+			.get_or_insert_with(|| "jackpot.txt".into());
+		options
+			.upgrades_file
+			.get_or_insert_with(|| "upgrades.txt".into());
+		options
+			.session_file
+			.get_or_insert_with(|| "session.txt".into());
+	}
+
+	let session = options
+		.session_file
+		.as_deref()
+		.filter(|path| path.exists())
+		.and_then(|path| match offline::Session::load(path) {
+			Ok(session) => Some(session),
+			Err(e) => {
+				eprintln!("Failed to load session from {path:?}: {e}");
+				None
+			}
+		});
+	// Before the real app, so it doesn't sit there without a window while this runs
+	let away = session
+		.as_ref()
+		.and_then(|session| offline::catch_up(&options, session, SystemTime::now()));
+
+	let mut app = App::new();
+	if options.headless {
+		app.add_plugins(headless::headless_plugins())
+			.insert_resource(headless::fixed_step_time());
+	} else {
+		app.add_plugins(DefaultPlugins)
+			.add_plugins((FrameTimeDiagnosticsPlugin, TemporalAntiAliasPlugin))
+			.add_plugins((
+				cam::CamPlugin,
+				env::EnvPlugin,
+				hopper::HopperPlugin,
+				tools::ToolsPlugin,
+				ui::UiPlugin,
+			));
+	}
+	add_simulation(&mut app, &options);
+	if let Some(session) = &session {
+		session.restore_auto_drop(&mut app, &options);
+		let credit = away.as_ref().map_or(0, |away| away.credit);
+		app.insert_resource(Winnings(from_cents(session.winnings_cents + credit)));
+	}
+	if let Some(away) = away {
+		println!("{away}");
+		app.insert_resource(away);
+	}
+	if let Some(path) = &options.session_file {
+		app.insert_resource(offline::SessionFile(path.clone()));
+	}
+
+	if options.headless {
+		let report = headless::simulate(&mut app, options.duration);
+		println!("{report}");
+		if let Some(path) = &options.lanes_csv {
+			if let Err(e) = report.lanes.save(path) {
+				eprintln!("Failed to write {path:?}: {e}");
+			}
+		}
+	} else {
+		app.run();
+	}
+}
+
+/// Everything needed to simulate the machine, with or without a window.
+pub fn add_simulation(app: &mut App, options: &cli::Options) {
+	let playback = options
+		.replay
+		.as_ref()
+		.map(|path| replay::Replay::load(path.clone()));
+	// Playback only goes the same way if everything rolls the same numbers again
+	let seed = match &playback {
+		Some(Ok(replay)) => replay.seed,
+		_ => None,
+	}
+	.or(options.seed)
+	.unwrap_or_else(rand::random);
+	app.add_plugins(PhysicsPlugins::default())
 		.add_plugins((
-			cam::CamPlugin,
+			auto_drop::AutoDropPlugin,
 			coins::CoinsPlugin,
-			env::EnvPlugin,
+			combos::CombosPlugin,
+			hinges::HingesPlugin,
+			house_edge::HouseEdgePlugin,
+			jackpot::JackpotPlugin,
+			lanes::LanesPlugin,
 			machine::MachinePlugin,
-			tools::ToolsPlugin,
-			ui::UiPlugin,
+			nudge::NudgePlugin,
+			obstacles::ObstaclesPlugin,
+			offline::OfflinePlugin,
+			pockets::PocketsPlugin,
+			prefill::PrefillPlugin,
+		))
+		.add_plugins((
+			power_ups::PowerUpsPlugin,
+			prizes::PrizesPlugin,
+			reels::ReelsPlugin,
+			replay::ReplayPlugin,
+			special::SpecialCoinsPlugin,
+			stats::StatsPlugin,
+			strategy::StrategyPlugin,
+			upgrades::UpgradesPlugin,
 		))
 		.init_resource::<Winnings>()
 		// Realistic gravity (772.44 half-inches/s^2 !!) causes too many problems
@@ -32,7 +175,132 @@ fn main() {
 		// to watch anyway.
 		.insert_resource(Gravity(Vector::NEG_Z * 20.0))
 		.insert_resource(SubstepCount(4))
-		.run();
+		.insert_resource(coins::DropRng::new(seed_for(seed, "drops")))
+		.insert_resource(special::SpecialCoinRng::new(seed_for(seed, "special")))
+		.insert_resource(auto_drop::AutoDropProgram::presets(&options.stop).remove(0))
+		.insert_resource(strategy::ActiveStrategy(
+			options
+				.strategy
+				.as_deref()
+				.and_then(|name| strategy::by_name(name, seed_for(seed, "strategy"))),
+		));
+
+	if let Some(pegs) = &options.pegs {
+		app.world_mut()
+			.resource_mut::<machine::MachineConfig>()
+			.pegs = pegs.clone();
+	}
+	if let Some(path) = &options.export_pegs {
+		let layout = app
+			.world()
+			.resource::<machine::MachineConfig>()
+			.pegs
+			.generate(pegs::PEG_FIELD);
+		match layout.validate() {
+			Err(e) => error!(?path, "Not exporting an invalid peg layout: {e}"),
+			Ok(()) => match layout.save(path) {
+				Ok(()) => info!(?path, pegs = layout.pegs.len(), "Exported peg layout"),
+				Err(e) => error!(?path, "Failed to export peg layout: {e}"),
+			},
+		}
+	}
+
+	match upgrades::Upgrades::load(options.upgrades_file.clone()) {
+		Ok(upgrades) => {
+			upgrades.apply(&mut app.world_mut().resource_mut::<machine::MachineConfig>());
+			app.insert_resource(upgrades);
+		}
+		Err(e) => error!(path = ?options.upgrades_file, "Failed to load upgrades: {e}"),
+	}
+
+	let jackpot = app
+		.world()
+		.resource::<machine::MachineConfig>()
+		.jackpot
+		.clone();
+	if let Some(config) = jackpot {
+		match jackpot::Jackpot::load(
+			&config,
+			options.jackpot_file.clone(),
+			seed_for(seed, "jackpot"),
+		) {
+			Ok(jackpot) => {
+				app.insert_resource(jackpot);
+			}
+			Err(e) => error!(path = ?options.jackpot_file, "Failed to load jackpot: {e}"),
+		}
+	}
+
+	let reels = app
+		.world()
+		.resource::<machine::MachineConfig>()
+		.reels
+		.clone();
+	if let Some(config) = reels {
+		match reels::Reels::new(config, seed_for(seed, "reels")) {
+			Ok(reels) => {
+				app.insert_resource(reels);
+			}
+			Err(e) => error!("Invalid reels, leaving them out: {e}"),
+		}
+	}
+
+	if let Some(max_force) = options.pusher_max_force {
+		app.world_mut()
+			.resource_mut::<machine::MachineConfig>()
+			.pusher_force_limit = Some(pusher::ForceLimit::new(
+			max_force,
+			options.pusher_jam_after,
+			options.pusher_recovery,
+		));
+	}
+
+	let replay = match &options.record {
+		Some(path) => Some(replay::Replay::record(path.clone(), seed)),
+		None => playback,
+	};
+	match replay {
+		Some(Ok(replay)) => {
+			app.insert_resource(replay);
+		}
+		Some(Err(e)) => error!("Failed to open replay: {e}"),
+		None => {}
+	}
+
+	if let Some(target_hold) = options.target_hold {
+		app.insert_resource(house_edge::HouseEdge::new(
+			target_hold,
+			options.house_edge_audit.clone(),
+		));
+	}
+
+	if options.prefill > 0 || options.prefill_shelf > 0 {
+		app.insert_resource(prefill::Prefill {
+			bed: options.prefill,
+			shelf: options.prefill_shelf,
+			seed: seed_for(seed, "prefill"),
+		})
+		.insert_state(GameState::Settling);
+	} else {
+		app.insert_state(GameState::Playing);
+	}
+}
+
+/// Seed for one source of randomness, derived from the run's `seed`. Each source gets its
+/// own stream, rather than them all rolling the same numbers in step.
+pub fn seed_for(seed: u64, name: &str) -> u64 {
+	// FNV-1a, to turn the name into something to mix in
+	let salt = name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash: u64, b| {
+		(hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+	});
+	StdRng::seed_from_u64(seed ^ salt).gen()
+}
+
+#[derive(States, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GameState {
+	/// Fast-forwarding with rendering off until pre-filled coins come to rest.
+	Settling,
+	Playing,
 }
 
 #[derive(Resource, Debug)]
@@ -43,3 +311,15 @@ impl Default for Winnings {
 		Self(Currency::from_str("$0.00").unwrap())
 	}
 }
+
+/// Whole cents in `amount`, for comparing and scaling amounts of money.
+pub fn cents(amount: &Currency) -> i64 {
+	// Anything that doesn't fit is far more than the machine could ever hold
+	amount.value().to_i64().unwrap_or(i64::MAX)
+}
+
+/// Inverse of [`cents`]. Negative amounts are clamped to zero.
+pub fn from_cents(cents: i64) -> Currency {
+	let cents = cents.max(0);
+	Currency::from_str(&format!("${}.{:02}", cents / 100, cents % 100)).unwrap()
+}
@@ -0,0 +1,183 @@
+use crate::coins::{drop_coins, CancelDrops, Coin, CoinDropReason, DropCoin, PendingShower};
+use crate::machine::{InTray, MachineConfig};
+use crate::pockets::CollectionMultiplier;
+use crate::prizes::Prize;
+use crate::reels::Reels;
+use crate::special::CollectionBoost;
+use crate::{cents, from_cents, GameState, Winnings};
+use avian3d::prelude::LinearVelocity;
+use bevy::prelude::*;
+use currency::Currency;
+use std::collections::VecDeque;
+
+/// Nudging the cabinet jolts everything on the platform. Nudge too often and the machine
+/// tilts. Configured by [`MachineConfig::nudge`].
+pub struct NudgePlugin;
+
+impl Plugin for NudgePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Tilt>()
+			.add_event::<Nudge>()
+			.add_event::<Tilted>()
+			.add_systems(
+				FixedUpdate,
+				(nudge, tilt, lock_drops)
+					.chain()
+					.before(drop_coins)
+					.run_if(in_state(GameState::Playing)),
+			);
+	}
+}
+
+/// Shoves the cabinet along the platform, as (across, toward the back). Its length is how
+/// hard, from 0.0 to 1.0.
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct Nudge(pub Vec2);
+
+#[derive(Debug, Clone)]
+pub struct NudgeConfig {
+	/// Speed a full strength nudge adds to everything on the platform.
+	pub speed: f32,
+	/// Nudges allowed in any `window` seconds. One more tilts the machine.
+	pub max_nudges: usize,
+	pub window: f32,
+	/// How long drops are locked out after a tilt.
+	pub lock_secs: f32,
+	/// Clear queued showers, reel spins and multipliers on a tilt.
+	pub forfeit_bonuses: bool,
+	/// Taken out of [`Winnings`] on a tilt, as far as they go.
+	pub fee: Currency,
+}
+
+impl Default for NudgeConfig {
+	fn default() -> Self {
+		Self {
+			speed: 3.0,
+			max_nudges: 3,
+			window: 5.0,
+			lock_secs: 10.0,
+			forfeit_bonuses: true,
+			fee: Currency::from_str("$5.00").unwrap(),
+		}
+	}
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Tilt {
+	/// When each nudge in the current window happened.
+	nudges: VecDeque<f32>,
+	/// Seconds left before drops are allowed again.
+	pub locked_for: f32,
+	pub tilts: u64,
+}
+
+impl Tilt {
+	pub fn is_locked(&self) -> bool {
+		self.locked_for > 0.0
+	}
+
+	/// Nudges left before the next one tilts the machine.
+	pub fn nudges_left(&self, config: &NudgeConfig) -> usize {
+		config.max_nudges.saturating_sub(self.nudges.len())
+	}
+}
+
+/// Sent when the machine tilts, after the penalty has been applied.
+#[derive(Event, Debug, Clone)]
+pub struct Tilted {
+	pub fee: Currency,
+}
+
+pub fn nudge(
+	mut events: EventReader<Nudge>,
+	// Coins in the payout tray have already been paid, and kicking them out would lose them
+	mut bodies: Query<&mut LinearVelocity, (Or<(With<Coin>, With<Prize>)>, Without<InTray>)>,
+	mut tilt: ResMut<Tilt>,
+	config: Res<MachineConfig>,
+	t: Res<Time>,
+) {
+	let Some(config) = &config.nudge else {
+		events.clear();
+		return;
+	};
+	for &Nudge(direction) in events.read() {
+		let direction = direction.clamp_length_max(1.0);
+		info!(?direction, "Nudge");
+		let kick = config.speed * direction.extend(0.0);
+		for mut vel in &mut bodies {
+			vel.0 += kick;
+		}
+		tilt.nudges.push_back(t.elapsed_secs());
+	}
+}
+
+pub fn tilt(
+	mut tilt: ResMut<Tilt>,
+	mut tilted: EventWriter<Tilted>,
+	mut cancels: EventWriter<CancelDrops>,
+	mut winnings: ResMut<Winnings>,
+	mut pending_shower: ResMut<PendingShower>,
+	mut multiplier: ResMut<CollectionMultiplier>,
+	mut boost: ResMut<CollectionBoost>,
+	reels: Option<ResMut<Reels>>,
+	config: Res<MachineConfig>,
+	t: Res<Time>,
+) {
+	let Some(config) = &config.nudge else {
+		return;
+	};
+	tilt.locked_for = (tilt.locked_for - t.delta_secs()).max(0.0);
+	let now = t.elapsed_secs();
+	while tilt
+		.nudges
+		.front()
+		.is_some_and(|&time| now - time > config.window)
+	{
+		tilt.nudges.pop_front();
+	}
+	if tilt.nudges.len() <= config.max_nudges {
+		return;
+	}
+
+	tilt.nudges.clear();
+	tilt.locked_for = config.lock_secs;
+	tilt.tilts += 1;
+	cancels.send(CancelDrops(CoinDropReason::Manual));
+	cancels.send(CancelDrops(CoinDropReason::Auto));
+	if config.forfeit_bonuses {
+		cancels.send(CancelDrops(CoinDropReason::Bonus));
+		*pending_shower = PendingShower::default();
+		*multiplier = CollectionMultiplier::default();
+		*boost = CollectionBoost::default();
+		if let Some(mut reels) = reels {
+			reels.stock = 0;
+		}
+	}
+	let fee = from_cents(cents(&config.fee).min(cents(&winnings.0)));
+	winnings.0 = from_cents(cents(&winnings.0) - cents(&fee));
+	warn!(%fee, "TILT");
+	tilted.send(Tilted { fee });
+}
+
+/// Throws away the player's and auto-drop's new drops while the machine is locked after a
+/// tilt. Free coins from bonuses only go too if [`NudgeConfig::forfeit_bonuses`] is set.
+pub fn lock_drops(
+	tilt: Res<Tilt>,
+	mut drops: ResMut<Events<DropCoin>>,
+	config: Res<MachineConfig>,
+) {
+	if !tilt.is_locked() || drops.is_empty() {
+		return;
+	}
+	let forfeit_bonuses = config
+		.nudge
+		.as_ref()
+		.is_some_and(|config| config.forfeit_bonuses);
+	let (kept, ignored): (Vec<_>, Vec<_>) = drops
+		.drain()
+		.partition(|ev| ev.reason == CoinDropReason::Bonus && !forfeit_bonuses);
+	info!(count = ignored.len(), "Machine is tilted, ignoring drops");
+	for ev in kept {
+		drops.send(ev);
+	}
+}
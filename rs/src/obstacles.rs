@@ -0,0 +1,153 @@
+use crate::pusher::PusherMotion;
+use crate::GameState;
+use avian3d::prelude::{AngularVelocity, Collider, LinearVelocity, RigidBody};
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// Kinematic windmills, bars and wheels moving around the peg board. Spawned by
+/// [`crate::machine::spawn_machine`] from [`crate::machine::MachineConfig::obstacles`].
+pub struct ObstaclesPlugin;
+
+impl Plugin for ObstaclesPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			FixedUpdate,
+			move_obstacles.run_if(in_state(GameState::Playing)),
+		);
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ObstacleConfig {
+	pub shape: ObstacleShape,
+	/// In the peg board's space, where X is across, Z is up and Y is out of the board.
+	pub motion: PusherMotion,
+}
+
+impl ObstacleConfig {
+	/// Spins around `center`, as (across, up) on the peg board, once every `period`
+	/// seconds. Negative periods spin clockwise.
+	pub fn spinning(shape: ObstacleShape, center: Vec2, period: f32) -> Self {
+		Self {
+			shape,
+			motion: PusherMotion::spinner(board_point(center), Vec3::NEG_Y, period),
+		}
+	}
+
+	/// Slides back and forth between `start` and `end`, as (across, up) on the peg board.
+	pub fn sliding(
+		shape: ObstacleShape,
+		start: Vec2,
+		end: Vec2,
+		duration: f32,
+		dwell: f32,
+	) -> Self {
+		Self {
+			shape,
+			motion: PusherMotion::linear(board_point(start), board_point(end), duration, dwell),
+		}
+	}
+}
+
+/// Between the board and the glass, like the pegs.
+pub const OBSTACLE_DEPTH: f32 = -2.6875;
+/// Front to back, so coins can't get stuck between obstacles and the board or glass.
+const THICKNESS: f32 = 0.3;
+
+fn board_point(p: Vec2) -> Vec3 {
+	Vec3::new(p.x, OBSTACLE_DEPTH, p.y)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ObstacleShape {
+	Bar {
+		length: f32,
+	},
+	/// `blades` bars of `length` meeting in the middle.
+	Windmill {
+		blades: usize,
+		length: f32,
+	},
+	/// A disc facing out of the board, which flings coins with friction.
+	Wheel {
+		radius: f32,
+	},
+}
+
+impl ObstacleShape {
+	pub fn collider(self) -> Collider {
+		match self {
+			Self::Bar { length } => Collider::cuboid(length, THICKNESS, 0.4),
+			Self::Windmill { blades, length } => Collider::compound(
+				(0..blades)
+					.map(|i| {
+						let rotation = Quat::from_rotation_y(TAU * i as f32 / blades as f32);
+						(
+							rotation * Vec3::X * 0.5 * length,
+							rotation,
+							Collider::cuboid(length, THICKNESS, 0.4),
+						)
+					})
+					.collect(),
+			),
+			Self::Wheel { radius } => Collider::cylinder(radius, THICKNESS),
+		}
+	}
+
+	pub fn mesh(self) -> Mesh {
+		match self {
+			Self::Bar { length } => Cuboid::new(length, THICKNESS, 0.4).into(),
+			Self::Windmill { blades, length } => {
+				let blade = Mesh::from(Cuboid::new(length, THICKNESS, 0.4))
+					.translated_by(Vec3::X * 0.5 * length);
+				let mut mesh = blade.clone();
+				for i in 1..blades {
+					mesh.merge(
+						&blade
+							.clone()
+							.rotated_by(Quat::from_rotation_y(TAU * i as f32 / blades as f32)),
+					);
+				}
+				mesh
+			}
+			Self::Wheel { radius } => Cylinder::new(radius, THICKNESS).into(),
+		}
+	}
+}
+
+#[derive(Component, Debug, Clone)]
+#[require(RigidBody(|| RigidBody::Kinematic), Collider)]
+pub struct Obstacle {
+	pub motion: PusherMotion,
+	/// The peg board's transform, which `motion` is relative to.
+	pub board: Transform,
+	/// Seconds into `motion`.
+	pub progress: f32,
+}
+
+pub fn move_obstacles(
+	mut q: Query<(
+		&mut Transform,
+		&mut LinearVelocity,
+		&mut AngularVelocity,
+		&mut Obstacle,
+	)>,
+	t: Res<Time<Fixed>>,
+) {
+	let dt = t.timestep().as_secs_f32();
+	for (mut xform, mut vel, mut ang_vel, mut obstacle) in &mut q {
+		let next = obstacle.progress + dt;
+		let Some(step) = obstacle
+			.motion
+			.step(&obstacle.board, obstacle.progress, next, dt)
+		else {
+			error!(t = obstacle.progress, "Failed to sample obstacle motion");
+			continue;
+		};
+		xform.translation = step.translation;
+		xform.rotation = step.rotation;
+		vel.0 = step.linear_velocity;
+		ang_vel.0 = step.angular_velocity;
+		obstacle.progress = next;
+	}
+}
@@ -0,0 +1,354 @@
+use crate::auto_drop::AutoDropProgram;
+use crate::cli::Options;
+use crate::coins::{ActiveChute, AutoDrop, AutoDropTimer, Coin};
+use crate::headless::{fixed_step_time, headless_plugins, simulate_with};
+use crate::jackpot::Jackpot;
+use crate::machine::{InTray, MachineConfig};
+use crate::reels::Reels;
+use crate::stats::PayoutStats;
+use crate::{add_simulation, cents, from_cents, Winnings};
+use bevy::app::AppExit;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Saves the session when the app closes, so that the next one can pick up the balance
+/// and, if auto-drop was left on, credit what it would have won in the meantime.
+/// See [`catch_up`].
+pub struct OfflinePlugin;
+
+impl Plugin for OfflinePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Last, save_session.run_if(resource_exists::<SessionFile>));
+	}
+}
+
+/// Gaps shorter than this aren't worth simulating.
+pub const MIN_AWAY: Duration = Duration::from_secs(60);
+/// Nothing is credited for time away beyond this.
+pub const MAX_AWAY: Duration = Duration::from_secs(8 * 60 * 60);
+/// Longest gap that is simulated in full. Longer ones simulate this much and estimate the
+/// rest from it.
+pub const MAX_SIMULATED: Duration = Duration::from_secs(10 * 60);
+/// For an estimate, how long to let the machine run before measuring it, so the pre-filled
+/// bed has settled and the piston has pushed it into its usual shape.
+pub const WARM_UP: Duration = Duration::from_secs(3 * 60);
+/// For an estimate, the payout rate is measured over samples this long.
+const SAMPLE: Duration = Duration::from_secs(60);
+
+/// Where the session is saved to.
+#[derive(Resource, Debug, Clone)]
+pub struct SessionFile(pub PathBuf);
+
+/// What's needed to carry on where the last session left off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+	/// Seconds since the Unix epoch.
+	pub saved_at: u64,
+	pub winnings_cents: i64,
+	pub auto_drop: bool,
+	pub auto_interval: Duration,
+	/// Name of the [`AutoDropProgram`].
+	pub program: String,
+	pub chute: usize,
+	/// Coins left on the machine, which an offline run pre-fills the bed with.
+	pub coins_on_bed: usize,
+}
+
+impl Session {
+	pub fn load(path: &Path) -> io::Result<Self> {
+		fs::read_to_string(path)?
+			.parse()
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		fs::write(path, self.to_string())
+	}
+
+	/// How long ago the session was saved, or zero if the clock went backwards.
+	pub fn away(&self, now: SystemTime) -> Duration {
+		let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+		now.saturating_sub(Duration::from_secs(self.saved_at))
+	}
+
+	/// Puts the auto-drop settings back how they were. Call after [`add_simulation`].
+	pub fn restore_auto_drop(&self, app: &mut App, options: &Options) {
+		let world = app.world_mut();
+		**world.resource_mut::<AutoDrop>() = self.auto_drop;
+		world
+			.resource_mut::<AutoDropTimer>()
+			.set_duration(self.auto_interval);
+		if let Some(program) = AutoDropProgram::presets(&options.stop)
+			.into_iter()
+			.find(|program| program.name == self.program)
+		{
+			world.insert_resource(program);
+		}
+		// The machine sets the active chute from this when it spawns
+		let mut config = world.resource_mut::<MachineConfig>();
+		if self.chute < config.chutes.len() {
+			config.default_chute = self.chute;
+		}
+	}
+}
+
+/// One `<key>=<value>` line per field.
+impl fmt::Display for Session {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "saved_at={}", self.saved_at)?;
+		writeln!(f, "winnings_cents={}", self.winnings_cents)?;
+		writeln!(f, "auto_drop={}", self.auto_drop)?;
+		writeln!(f, "auto_interval_ms={}", self.auto_interval.as_millis())?;
+		writeln!(f, "program={}", self.program)?;
+		writeln!(f, "chute={}", self.chute)?;
+		writeln!(f, "coins_on_bed={}", self.coins_on_bed)
+	}
+}
+
+impl FromStr for Session {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut session = Self {
+			saved_at: 0,
+			winnings_cents: 0,
+			auto_drop: false,
+			auto_interval: Duration::from_secs(2),
+			program: String::new(),
+			chute: 0,
+			coins_on_bed: 0,
+		};
+		for line in s.lines().filter(|line| !line.trim().is_empty()) {
+			let err = || format!("Invalid session line `{line}`");
+			let (key, value) = line.split_once('=').ok_or_else(err)?;
+			match key {
+				"saved_at" => session.saved_at = value.parse().map_err(|_| err())?,
+				"winnings_cents" => session.winnings_cents = value.parse().map_err(|_| err())?,
+				"auto_drop" => session.auto_drop = value.parse().map_err(|_| err())?,
+				"auto_interval_ms" => {
+					session.auto_interval =
+						Duration::from_millis(value.parse().map_err(|_| err())?);
+				}
+				"program" => session.program = value.into(),
+				"chute" => session.chute = value.parse().map_err(|_| err())?,
+				"coins_on_bed" => session.coins_on_bed = value.parse().map_err(|_| err())?,
+				// Left by a newer version
+				_ => {}
+			}
+		}
+		Ok(session)
+	}
+}
+
+/// What auto-drop won while the app was closed. Already added to [`Winnings`].
+#[derive(Resource, Debug, Clone)]
+pub struct AwaySummary {
+	/// Time away, up to [`MAX_AWAY`].
+	pub away: Duration,
+	/// How much of that was actually simulated.
+	pub simulated: Duration,
+	pub coins_dropped: u64,
+	pub credit: i64,
+	/// For an estimate, how far off `credit` is likely to be (one standard error).
+	pub spread: Option<i64>,
+}
+
+impl AwaySummary {
+	/// Whether the totals were estimated from a shorter simulation.
+	pub fn is_estimate(&self) -> bool {
+		self.spread.is_some()
+	}
+}
+
+impl fmt::Display for AwaySummary {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mins = self.away.as_secs() / 60;
+		writeln!(f, "While you were away ({}h {:02}m):", mins / 60, mins % 60)?;
+		let about = if self.is_estimate() { "about " } else { "" };
+		writeln!(f, "Auto-drop dropped {about}{} coins", self.coins_dropped)?;
+		write!(f, "and won {about}{}", from_cents(self.credit))?;
+		if let Some(spread) = self.spread {
+			write!(f, " (give or take {})", from_cents(spread))?;
+		}
+		Ok(())
+	}
+}
+
+/// Simulates auto-drop over the time since `session` was saved, if it was left on.
+///
+/// Gaps longer than [`MAX_SIMULATED`] are estimated instead. After a [`WARM_UP`], the rest
+/// of the run is split into samples of what auto-drop spent and won, and their average rate
+/// is extended over the whole gap. One-off bonuses would be extended along with it, so the
+/// reels, combos, prizes and special coins are left out of an estimate. The jackpot is
+/// always left out, since its pool belongs to the real session.
+pub fn catch_up(options: &Options, session: &Session, now: SystemTime) -> Option<AwaySummary> {
+	let away = session.away(now);
+	if !session.auto_drop || away < MIN_AWAY {
+		return None;
+	}
+	let away = away.min(MAX_AWAY);
+	let simulated = away.min(MAX_SIMULATED);
+	let estimate = simulated < away;
+
+	let mut options = options.clone();
+	options.headless = true;
+	// Shouldn't touch anything the real session keeps
+	options.jackpot_file = None;
+	options.session_file = None;
+	options.record = None;
+	options.replay = None;
+	options.strategy = None;
+	options.prefill = session.coins_on_bed;
+	options.prefill_shelf = 0;
+
+	let mut app = App::new();
+	// The real app sets up logging once this is done, and it can only be set up once
+	app.add_plugins(headless_plugins().build().disable::<LogPlugin>())
+		.insert_resource(fixed_step_time());
+	add_simulation(&mut app, &options);
+	session.restore_auto_drop(&mut app, &options);
+	let world = app.world_mut();
+	world.remove_resource::<Jackpot>();
+	if estimate {
+		world.remove_resource::<Reels>();
+		let mut config = world.resource_mut::<MachineConfig>();
+		config.combo = None;
+		config.prizes.clear();
+		config.special_coins.clear();
+	}
+
+	// Coins dropped and cents won in each sample
+	let mut samples = Vec::new();
+	let mut last = None;
+	let mut next_sample = WARM_UP;
+	let mut shown = 0;
+	let report = simulate_with(&mut app, simulated, |world| {
+		let elapsed = world.resource::<Time<Virtual>>().elapsed();
+		let pct = (100.0 * elapsed.as_secs_f64() / simulated.as_secs_f64()) as u32;
+		if pct >= shown + 10 {
+			shown = pct - pct % 10;
+			eprint!("\rCatching up on time away: {shown}%");
+		}
+		if !estimate || elapsed < next_sample {
+			return;
+		}
+		let totals = (
+			world.resource::<PayoutStats>().coins_dropped,
+			cents(&world.resource::<Winnings>().0),
+		);
+		if let Some((coins, won)) = last {
+			samples.push(((totals.0 - coins) as f64, (totals.1 - won) as f64));
+		}
+		last = Some(totals);
+		next_sample += SAMPLE;
+	});
+	eprintln!();
+	let won = cents(&app.world().resource::<Winnings>().0);
+
+	// Nothing to extend if the run stopped itself, e.g. on a stop-loss
+	if !estimate || !**app.world().resource::<AutoDrop>() || samples.is_empty() {
+		return Some(AwaySummary {
+			away,
+			simulated,
+			coins_dropped: report.stats.coins_dropped,
+			credit: won,
+			spread: None,
+		});
+	}
+	let n = samples.len() as f64;
+	let mean_coins = samples.iter().map(|&(coins, _)| coins).sum::<f64>() / n;
+	let mean_won = samples.iter().map(|&(_, won)| won).sum::<f64>() / n;
+	let variance = samples
+		.iter()
+		.map(|&(_, won)| (won - mean_won).powi(2))
+		.sum::<f64>()
+		/ (n - 1.0).max(1.0);
+	let scale = away.as_secs_f64() / SAMPLE.as_secs_f64();
+	Some(AwaySummary {
+		away,
+		simulated,
+		coins_dropped: (mean_coins * scale).round() as u64,
+		credit: (mean_won * scale).round() as i64,
+		spread: Some(((variance / n).sqrt() * scale).round() as i64),
+	})
+}
+
+/// Saves the session every so often, and on exit.
+pub fn save_session(
+	file: Res<SessionFile>,
+	winnings: Res<Winnings>,
+	auto: Res<AutoDrop>,
+	timer: Res<AutoDropTimer>,
+	program: Res<AutoDropProgram>,
+	chute: Res<ActiveChute>,
+	coins: Query<(), (With<Coin>, Without<InTray>)>,
+	mut exit: EventReader<AppExit>,
+	time: Res<Time<Real>>,
+	mut last_save: Local<Duration>,
+) {
+	let exiting = exit.read().count() > 0;
+	if !exiting && time.elapsed() - *last_save < Duration::from_secs(30) {
+		return;
+	}
+	*last_save = time.elapsed();
+	let session = Session {
+		saved_at: SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs(),
+		winnings_cents: cents(&winnings.0),
+		auto_drop: **auto,
+		auto_interval: timer.duration(),
+		program: program.name.clone(),
+		chute: **chute,
+		coins_on_bed: coins.iter().count(),
+	};
+	if let Err(e) = session.save(&file.0) {
+		error!(path = ?file.0, "Failed to save session: {e}");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn session_round_trips() {
+		let session = Session {
+			saved_at: 1_700_000_000,
+			winnings_cents: 12_345,
+			auto_drop: true,
+			auto_interval: Duration::from_millis(300),
+			program: "Burst x5".into(),
+			chute: 2,
+			coins_on_bed: 180,
+		};
+		assert_eq!(session.to_string().parse::<Session>(), Ok(session));
+	}
+
+	#[test]
+	fn session_skips_unknown_keys() {
+		let session = "saved_at=5\nadded_later=1\n".parse::<Session>().unwrap();
+		assert_eq!(session.saved_at, 5);
+	}
+
+	#[test]
+	fn session_rejects_bad_values() {
+		assert!("winnings_cents=lots".parse::<Session>().is_err());
+		assert!("auto_drop".parse::<Session>().is_err());
+	}
+
+	#[test]
+	fn away_is_zero_if_the_clock_went_backwards() {
+		let session = "saved_at=100".parse::<Session>().unwrap();
+		let now = UNIX_EPOCH + Duration::from_secs(160);
+		assert_eq!(session.away(now), Duration::from_secs(60));
+		assert_eq!(session.away(UNIX_EPOCH), Duration::ZERO);
+	}
+}
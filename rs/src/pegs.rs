@@ -0,0 +1,316 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::FRAC_PI_3;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const PEG_RADIUS: f32 = 0.25;
+pub const COIN_DIAMETER: f32 = 2.0;
+/// Closest two pegs can be, center to center, and still let a coin through between them.
+pub const MIN_PEG_SPACING: f32 = 2.0 * PEG_RADIUS + COIN_DIAMETER;
+/// Inner faces of the machine's side walls, either side of the peg board's center.
+pub const WALL_X: f32 = 10.0;
+/// Closest a peg can be to a side wall, center to wall, and still let a coin through.
+pub const MIN_WALL_SPACING: f32 = PEG_RADIUS + COIN_DIAMETER;
+
+/// Where on the peg board pegs may go, as (across, up) from its center. Leaves room for
+/// coins between the outermost pegs and the walls.
+pub const PEG_FIELD: Rect = Rect {
+	min: Vec2::new(MIN_WALL_SPACING - WALL_X, -10.0),
+	max: Vec2::new(WALL_X - MIN_WALL_SPACING, 14.0),
+};
+
+/// How to lay out pegs in a field, see [`PegPattern::generate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PegPattern {
+	/// Rows of `columns` pegs, every other row shifted right by `offset`.
+	OffsetGrid {
+		rows: usize,
+		columns: usize,
+		spacing: Vec2,
+		offset: f32,
+	},
+	/// Equilateral triangles with sides of `spacing`, filling the field.
+	Hex { spacing: f32 },
+	/// A triangle of `rows` rows hanging from the top middle of the field, like a Galton board.
+	Galton { rows: usize, spacing: f32 },
+	/// Up to `count` pegs anywhere in the field, at least `min_spacing` apart.
+	Random {
+		count: usize,
+		min_spacing: f32,
+		seed: u64,
+	},
+	/// A layout that was generated before, see [`PegLayout::save`].
+	Custom(PegLayout),
+}
+
+impl Default for PegPattern {
+	fn default() -> Self {
+		Self::OffsetGrid {
+			rows: 7,
+			columns: 5,
+			spacing: Vec2::new(3.5, 4.0),
+			offset: 1.5,
+		}
+	}
+}
+
+impl PegPattern {
+	/// Names accepted by [`PegPattern::from_str`].
+	pub const NAMES: &'static [&'static str] = &["grid", "hex", "galton", "random[:<seed>]"];
+
+	pub fn generate(&self, field: Rect) -> PegLayout {
+		let row_height = |spacing: f32| spacing * FRAC_PI_3.sin();
+		let pegs = match self {
+			&Self::OffsetGrid {
+				rows,
+				columns,
+				spacing,
+				offset,
+			} => (0..rows)
+				.flat_map(|v| {
+					(0..columns).map(move |h| {
+						field.min
+							+ Vec2::new(
+								h as f32 * spacing.x + offset * (v % 2) as f32,
+								v as f32 * spacing.y,
+							)
+					})
+				})
+				.collect(),
+			&Self::Hex { spacing } => {
+				let spacing = spacing.max(MIN_PEG_SPACING);
+				let rows = (field.height() / row_height(spacing)) as usize + 1;
+				let mut pegs = Vec::new();
+				for v in 0..rows {
+					let mut x = field.min.x + 0.5 * spacing * (v % 2) as f32;
+					while x <= field.max.x {
+						pegs.push(Vec2::new(x, field.min.y + v as f32 * row_height(spacing)));
+						x += spacing;
+					}
+				}
+				pegs
+			}
+			&Self::Galton { rows, spacing } => (0..rows)
+				.flat_map(|v| {
+					(0..=v).map(move |h| {
+						Vec2::new(
+							field.center().x + (h as f32 - 0.5 * v as f32) * spacing,
+							field.max.y - v as f32 * row_height(spacing),
+						)
+					})
+				})
+				.filter(|peg| field.contains(*peg))
+				.collect(),
+			&Self::Random {
+				count,
+				min_spacing,
+				seed,
+			} => {
+				let min_spacing = min_spacing.max(MIN_PEG_SPACING);
+				let mut rng = StdRng::seed_from_u64(seed);
+				let mut pegs: Vec<Vec2> = Vec::with_capacity(count);
+				// Gives up once the field is too full to find room
+				for _ in 0..count * 30 {
+					if pegs.len() == count {
+						break;
+					}
+					let peg = Vec2::new(
+						rng.gen_range(field.min.x..=field.max.x),
+						rng.gen_range(field.min.y..=field.max.y),
+					);
+					if pegs.iter().all(|other| other.distance(peg) >= min_spacing) {
+						pegs.push(peg);
+					}
+				}
+				pegs
+			}
+			Self::Custom(layout) => layout.pegs.clone(),
+		};
+		PegLayout { pegs }
+	}
+}
+
+impl FromStr for PegPattern {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, seed) = s.split_once(':').unwrap_or((s, ""));
+		match name {
+			"grid" => Ok(Self::default()),
+			"hex" => Ok(Self::Hex { spacing: 4.0 }),
+			"galton" => Ok(Self::Galton {
+				rows: 7,
+				spacing: 3.5,
+			}),
+			"random" => Ok(Self::Random {
+				count: 30,
+				min_spacing: 3.0,
+				seed: if seed.is_empty() {
+					0
+				} else {
+					seed.parse().map_err(|_| format!("Invalid seed `{seed}`"))?
+				},
+			}),
+			_ => Err(format!(
+				"Unknown peg pattern `{name}`, expected one of {:?}",
+				Self::NAMES
+			)),
+		}
+	}
+}
+
+/// Peg positions, as (across, up) from the center of the peg board.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PegLayout {
+	pub pegs: Vec<Vec2>,
+}
+
+impl PegLayout {
+	/// Checks that a coin fits between every pair of pegs, and between each peg and the
+	/// side walls, so none can get wedged.
+	pub fn validate(&self) -> Result<(), PegLayoutError> {
+		for (i, a) in self.pegs.iter().enumerate() {
+			let to_wall = WALL_X - a.x.abs();
+			if to_wall < MIN_WALL_SPACING {
+				return Err(PegLayoutError::TooCloseToWall(*a, to_wall));
+			}
+			for b in &self.pegs[i + 1..] {
+				let distance = a.distance(*b);
+				if distance < MIN_PEG_SPACING {
+					return Err(PegLayoutError::TooClose(*a, *b, distance));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	pub fn load(path: &Path) -> io::Result<Self> {
+		fs::read_to_string(path)?
+			.parse()
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		fs::write(path, self.to_string())
+	}
+}
+
+/// One `x,y` line per peg.
+impl fmt::Display for PegLayout {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for peg in &self.pegs {
+			writeln!(f, "{},{}", peg.x, peg.y)?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for PegLayout {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let pegs = s
+			.lines()
+			.filter(|line| !line.trim().is_empty())
+			.map(|line| {
+				let err = || format!("Expected `<x>,<y>`, got `{line}`");
+				let (x, y) = line.split_once(',').ok_or_else(err)?;
+				Ok(Vec2::new(
+					x.trim().parse().map_err(|_| err())?,
+					y.trim().parse().map_err(|_| err())?,
+				))
+			})
+			.collect::<Result<_, String>>()?;
+		Ok(Self { pegs })
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PegLayoutError {
+	/// Two pegs, and the distance between them.
+	TooClose(Vec2, Vec2, f32),
+	/// A peg, and the distance from its center to the nearest side wall.
+	TooCloseToWall(Vec2, f32),
+}
+
+impl fmt::Display for PegLayoutError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::TooClose(a, b, distance) => write!(
+				f,
+				"Pegs at {a} and {b} are {distance:.2} apart, coins need {MIN_PEG_SPACING:.2}"
+			),
+			Self::TooCloseToWall(peg, distance) => write!(
+				f,
+				"Peg at {peg} is {distance:.2} from the wall, coins need {MIN_WALL_SPACING:.2}"
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn every_named_pattern_fits_the_field() {
+		for name in ["grid", "hex", "galton", "random", "random:7"] {
+			let layout = name.parse::<PegPattern>().unwrap().generate(PEG_FIELD);
+			assert!(!layout.pegs.is_empty(), "{name}");
+			assert!(
+				layout.pegs.iter().all(|peg| PEG_FIELD.contains(*peg)),
+				"{name}"
+			);
+			assert_eq!(layout.validate(), Ok(()), "{name}");
+		}
+	}
+
+	#[test]
+	fn random_layouts_repeat_for_the_same_seed() {
+		let pattern = "random:3".parse::<PegPattern>().unwrap();
+		assert_eq!(pattern.generate(PEG_FIELD), pattern.generate(PEG_FIELD));
+	}
+
+	#[test]
+	fn tiny_hex_spacing_is_clamped() {
+		let layout = PegPattern::Hex { spacing: 1e-9 }.generate(PEG_FIELD);
+		assert_eq!(layout.pegs[1].x - layout.pegs[0].x, MIN_PEG_SPACING);
+	}
+
+	#[test]
+	fn pegs_too_close_together_are_invalid() {
+		let layout = PegLayout {
+			pegs: vec![Vec2::ZERO, Vec2::new(1.0, 0.0)],
+		};
+		assert_eq!(
+			layout.validate(),
+			Err(PegLayoutError::TooClose(
+				Vec2::ZERO,
+				Vec2::new(1.0, 0.0),
+				1.0
+			))
+		);
+	}
+
+	#[test]
+	fn pegs_too_close_to_a_wall_are_invalid() {
+		let layout = PegLayout {
+			pegs: vec![Vec2::new(-9.0, 0.0)],
+		};
+		assert_eq!(
+			layout.validate(),
+			Err(PegLayoutError::TooCloseToWall(Vec2::new(-9.0, 0.0), 1.0))
+		);
+	}
+
+	#[test]
+	fn layouts_round_trip() {
+		let layout = PegPattern::default().generate(PEG_FIELD);
+		assert_eq!(layout.to_string().parse::<PegLayout>(), Ok(layout));
+	}
+}
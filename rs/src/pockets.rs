@@ -0,0 +1,173 @@
+use crate::coins::{Coin, CoinDropReason, CoinShower, DropCoin};
+use crate::machine::MachineConfig;
+use crate::{cents, from_cents, GameState, Winnings};
+use avian3d::prelude::{Collider, Collisions, Sensor};
+use bevy::prelude::*;
+use currency::Currency;
+
+/// Sensors that reward the player when a coin passes through them. Spawned by
+/// [`crate::machine::spawn_machine`] from [`crate::machine::MachineConfig::pockets`].
+pub struct PocketsPlugin;
+
+impl Plugin for PocketsPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<CollectionMultiplier>()
+			.add_event::<PocketHit>()
+			.add_systems(
+				FixedUpdate,
+				(detect_hits, reward_hits)
+					.chain()
+					.run_if(in_state(GameState::Playing)),
+			);
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PocketConfig {
+	pub name: String,
+	pub place: PocketPlace,
+	/// Full size of the sensor.
+	pub size: Vec3,
+	pub reward: Reward,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PocketPlace {
+	/// (across, up) from the center of the peg board.
+	Board(Vec2),
+	/// Anywhere else, e.g. at the back of the bed.
+	World(Vec3),
+}
+
+#[derive(Debug, Clone)]
+pub enum Reward {
+	/// Added straight to [`Winnings`].
+	Credit(Currency),
+	/// Free coins, dropped through the active chute.
+	ExtraCoins(usize),
+	/// Free coins, dumped straight onto the platform all at once.
+	Shower(usize),
+	/// The next `collections` coins collected pay `factor` times their value.
+	Multiplier { factor: f32, collections: u32 },
+	/// Wins the [`crate::jackpot::Jackpot`].
+	Jackpot,
+	/// Spins the [`crate::reels::Reels`].
+	SpinReels,
+}
+
+#[derive(Component, Debug, Clone)]
+#[require(Collider, Sensor)]
+pub struct BonusPocket {
+	/// Index into [`crate::machine::MachineConfig::pockets`].
+	pub index: usize,
+	pub reward: Reward,
+}
+
+/// Sent once each time a coin enters a [`BonusPocket`].
+#[derive(Event, Debug, Clone)]
+pub struct PocketHit {
+	pub pocket: usize,
+	pub coin: Entity,
+	pub reward: Reward,
+}
+
+/// Set by [`Reward::Multiplier`], applied by [`crate::machine::collect`].
+#[derive(Resource, Debug, Clone)]
+pub struct CollectionMultiplier {
+	pub factor: f32,
+	/// Collections left before `factor` goes back to 1.
+	pub remaining: u32,
+}
+
+impl Default for CollectionMultiplier {
+	fn default() -> Self {
+		Self {
+			factor: 1.0,
+			remaining: 0,
+		}
+	}
+}
+
+impl CollectionMultiplier {
+	/// What a coin worth `value` pays out, using up one collection.
+	pub fn apply(&mut self, value: &Currency) -> Currency {
+		if self.remaining == 0 {
+			return value.clone();
+		}
+		self.remaining -= 1;
+		from_cents((cents(value) as f32 * self.factor).round() as i64)
+	}
+}
+
+pub fn detect_hits(
+	pockets: Query<(Entity, &BonusPocket)>,
+	coins: Query<(), With<Coin>>,
+	collisions: Res<Collisions>,
+	mut events: EventWriter<PocketHit>,
+	// (pocket, coin) pairs touching on the last tick
+	mut touching: Local<Vec<(Entity, Entity)>>,
+) {
+	let mut now_touching = Vec::new();
+	for (pocket_id, pocket) in &pockets {
+		for contacts in collisions.collisions_with_entity(pocket_id) {
+			let coin = if contacts.entity1 == pocket_id {
+				contacts.entity2
+			} else {
+				contacts.entity1
+			};
+			if !coins.contains(coin) {
+				continue;
+			}
+			now_touching.push((pocket_id, coin));
+			if !touching.contains(&(pocket_id, coin)) {
+				events.send(PocketHit {
+					pocket: pocket.index,
+					coin,
+					reward: pocket.reward.clone(),
+				});
+			}
+		}
+	}
+	*touching = now_touching;
+}
+
+pub fn reward_hits(
+	mut hits: EventReader<PocketHit>,
+	mut winnings: ResMut<Winnings>,
+	mut multiplier: ResMut<CollectionMultiplier>,
+	mut drops: EventWriter<DropCoin>,
+	mut showers: EventWriter<CoinShower>,
+	config: Res<MachineConfig>,
+) {
+	for hit in hits.read() {
+		info!(pocket = hit.pocket, reward = ?hit.reward, "Bonus pocket");
+		match &hit.reward {
+			Reward::Credit(value) => {
+				winnings.0 = winnings.0.clone() + value.clone();
+			}
+			&Reward::ExtraCoins(count) => {
+				for _ in 0..count {
+					drops.send(DropCoin::new(
+						config.coin_value.clone(),
+						CoinDropReason::Bonus,
+					));
+				}
+			}
+			&Reward::Multiplier {
+				factor,
+				collections,
+			} => {
+				// Replaces whatever multiplier was active, rather than stacking
+				*multiplier = CollectionMultiplier {
+					factor,
+					remaining: collections,
+				};
+			}
+			&Reward::Shower(count) => {
+				showers.send(CoinShower::new(count, config.coin_value.clone()));
+			}
+			// Paid by the jackpot and reels themselves
+			Reward::Jackpot | Reward::SpinReels => {}
+		}
+	}
+}
@@ -0,0 +1,180 @@
+use crate::coins::Coin;
+use crate::machine::{move_piston, MachineConfig, Piston};
+use crate::special::CollectionBoost;
+use crate::{cents, from_cents, GameState, Winnings};
+use avian3d::prelude::LinearVelocity;
+use bevy::prelude::*;
+use currency::Currency;
+use std::fmt;
+
+/// Timed effects the player buys with their [`Winnings`]. What's on offer is
+/// [`MachineConfig::power_ups`].
+pub struct PowerUpsPlugin;
+
+impl Plugin for PowerUpsPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<PowerUps>()
+			.add_event::<BuyPowerUp>()
+			.add_event::<PowerUpBought>()
+			.add_systems(
+				FixedUpdate,
+				(buy_power_ups, run_power_ups, pull_coins)
+					.chain()
+					.before(move_piston)
+					.run_if(in_state(GameState::Playing)),
+			);
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PowerUpKind {
+	/// Doubles the piston's speed.
+	FastPiston,
+	/// Pulls coins on the platform toward the front edge.
+	Magnet,
+	/// Doubles what every collected coin pays, through [`CollectionBoost`].
+	DoubleValue,
+}
+
+impl fmt::Display for PowerUpKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::FastPiston => "Fast piston",
+			Self::Magnet => "Magnet",
+			Self::DoubleValue => "Double value",
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PowerUpConfig {
+	pub kind: PowerUpKind,
+	pub cost: Currency,
+	pub secs: f32,
+}
+
+impl PowerUpConfig {
+	pub fn new(kind: PowerUpKind, cost: &str, secs: f32) -> Self {
+		Self {
+			kind,
+			cost: Currency::from_str(cost).unwrap(),
+			secs,
+		}
+	}
+}
+
+/// Pull of a [`PowerUpKind::Magnet`], in units per second squared.
+pub const MAGNET_ACCEL: f32 = 4.0;
+
+/// Buys the power-up at this index into [`MachineConfig::power_ups`], if the player can
+/// afford it. Buying one that's already active adds to its time.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BuyPowerUp(pub usize);
+
+#[derive(Event, Debug, Clone)]
+pub struct PowerUpBought {
+	pub kind: PowerUpKind,
+	pub cost: Currency,
+}
+
+/// Power-ups that are running, with the seconds they have left.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PowerUps {
+	pub active: Vec<(PowerUpKind, f32)>,
+}
+
+impl PowerUps {
+	pub fn is_active(&self, kind: PowerUpKind) -> bool {
+		self.active.iter().any(|&(active, _)| active == kind)
+	}
+}
+
+pub fn buy_power_ups(
+	mut events: EventReader<BuyPowerUp>,
+	mut power_ups: ResMut<PowerUps>,
+	mut winnings: ResMut<Winnings>,
+	mut bought: EventWriter<PowerUpBought>,
+	config: Res<MachineConfig>,
+) {
+	for &BuyPowerUp(index) in events.read() {
+		let Some(offer) = config.power_ups.get(index) else {
+			warn!(index, "No such power-up");
+			continue;
+		};
+		let balance = cents(&winnings.0);
+		if balance < cents(&offer.cost) {
+			info!(kind = %offer.kind, cost = %offer.cost, "Can't afford power-up");
+			continue;
+		}
+		winnings.0 = from_cents(balance - cents(&offer.cost));
+		match power_ups
+			.active
+			.iter_mut()
+			.find(|(kind, _)| *kind == offer.kind)
+		{
+			Some((_, secs)) => *secs += offer.secs,
+			None => power_ups.active.push((offer.kind, offer.secs)),
+		}
+		info!(kind = %offer.kind, cost = %offer.cost, "Bought power-up");
+		bought.send(PowerUpBought {
+			kind: offer.kind,
+			cost: offer.cost.clone(),
+		});
+	}
+}
+
+/// Counts down active power-ups and applies the ones that change something persistent.
+pub fn run_power_ups(
+	mut power_ups: ResMut<PowerUps>,
+	mut pistons: Query<&mut Piston>,
+	mut boost: ResMut<CollectionBoost>,
+	t: Res<Time>,
+) {
+	let dt = t.delta_secs();
+	for (_, secs) in &mut power_ups.active {
+		*secs -= dt;
+	}
+	power_ups.active.retain(|&(kind, secs)| {
+		if secs <= 0.0 {
+			info!(%kind, "Power-up ran out");
+		}
+		secs > 0.0
+	});
+
+	let piston_boost = if power_ups.is_active(PowerUpKind::FastPiston) {
+		2.0
+	} else {
+		1.0
+	};
+	for mut piston in &mut pistons {
+		if piston.boost != piston_boost {
+			piston.boost = piston_boost;
+		}
+	}
+	if let Some(&(_, secs)) = power_ups
+		.active
+		.iter()
+		.find(|&&(kind, _)| kind == PowerUpKind::DoubleValue)
+	{
+		// Kept topped up rather than started once, so a multiplier coin can't cut it short
+		boost.start(2.0, secs);
+	}
+}
+
+pub fn pull_coins(
+	power_ups: Res<PowerUps>,
+	mut coins: Query<(&GlobalTransform, &mut LinearVelocity), With<Coin>>,
+	t: Res<Time>,
+) {
+	if !power_ups.is_active(PowerUpKind::Magnet) {
+		return;
+	}
+	let dv = MAGNET_ACCEL * t.delta_secs();
+	for (xform, mut vel) in &mut coins {
+		let pos = xform.translation();
+		// Only coins lying on the floor or the piston, not falling through the pegs
+		if pos.z > 2.0 && pos.z < 10.0 && pos.y > -20.0 {
+			vel.y -= dv;
+		}
+	}
+}
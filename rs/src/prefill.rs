@@ -0,0 +1,192 @@
+use crate::coins::{coin_bundle, setup_coins, Coin, CoinScene};
+use crate::machine::MachineConfig;
+use crate::GameState;
+use avian3d::math::FRAC_PI_2;
+use avian3d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+pub struct PrefillPlugin;
+
+impl Plugin for PrefillPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Startup,
+			(prefill, fast_forward)
+				.after(setup_coins)
+				.run_if(resource_exists::<Prefill>),
+		)
+		.add_systems(
+			Update,
+			suppress_rendering.run_if(in_state(GameState::Settling)),
+		)
+		.add_systems(
+			FixedUpdate,
+			(
+				check_settled.run_if(in_state(GameState::Settling)),
+				check_moved.run_if(in_state(GameState::Playing)),
+			),
+		)
+		.add_systems(OnExit(GameState::Settling), finish_settling);
+	}
+}
+
+/// How fast the simulation runs while the bed is settling.
+const SETTLE_SPEED: f32 = 16.0;
+/// Give up waiting for the bed to settle after this many simulated seconds.
+const MAX_SETTLE_SECS: f32 = 120.0;
+/// Consecutive quiet ticks required before the bed counts as settled.
+const SETTLED_TICKS: u32 = 64;
+/// Pre-filled coins start counting toward stats once they move this far from where they settled.
+const MOVED_DISTANCE: f32 = 1.0;
+const COIN_SPACING: f32 = 2.2;
+const COIN_THICKNESS: f32 = 0.25;
+
+/// Coins to place on the machine before the player gets control.
+#[derive(Resource, Debug, Clone)]
+pub struct Prefill {
+	/// Coins on the floor in front of the piston.
+	pub bed: usize,
+	/// Coins on top of the piston, behind its front edge.
+	pub shelf: usize,
+	pub seed: u64,
+}
+
+/// A coin that was placed by [`Prefill`] and hasn't been moved since the bed settled.
+#[derive(Component, Debug, Clone)]
+pub struct Prefilled {
+	pub origin: Vec3,
+}
+
+pub fn prefill(
+	mut cmds: Commands,
+	config: Res<Prefill>,
+	machine: Res<MachineConfig>,
+	coin_scene: Res<CoinScene>,
+) {
+	let mut rng = StdRng::seed_from_u64(config.seed);
+	// Floor top, and piston top while fully retracted
+	let bed = fill_region(
+		Vec2::new(-9.0, -19.0),
+		Vec2::new(9.0, -1.0),
+		2.5,
+		config.bed,
+		&mut rng,
+	);
+	let shelf = fill_region(
+		Vec2::new(-9.0, 0.5),
+		Vec2::new(9.0, 8.0),
+		7.5,
+		config.shelf,
+		&mut rng,
+	);
+	info!(
+		bed = config.bed,
+		shelf = config.shelf,
+		"Pre-filling machine"
+	);
+	for translation in bed.into_iter().chain(shelf) {
+		cmds.spawn((
+			coin_bundle(
+				Coin {
+					value: machine.coin_value.clone(),
+				},
+				&coin_scene,
+				Transform {
+					translation,
+					rotation: Quat::from_rotation_x(FRAC_PI_2)
+						* Quat::from_rotation_y(rng.gen::<f32>() * std::f32::consts::TAU),
+					..default()
+				},
+			),
+			Prefilled {
+				origin: translation,
+			},
+		));
+	}
+}
+
+/// Lays coins flat in jittered, shuffled grid cells, one layer at a time.
+fn fill_region(min: Vec2, max: Vec2, base_z: f32, count: usize, rng: &mut impl Rng) -> Vec<Vec3> {
+	let cols = ((max.x - min.x) / COIN_SPACING).floor().max(1.0) as usize;
+	let rows = ((max.y - min.y) / COIN_SPACING).floor().max(1.0) as usize;
+	let mut cells = (0..cols)
+		.flat_map(|col| (0..rows).map(move |row| (col, row)))
+		.collect::<Vec<_>>();
+	let jitter = 0.5 * (COIN_SPACING - 2.0);
+	let mut positions = Vec::with_capacity(count);
+	for layer in 0.. {
+		if positions.len() >= count {
+			break;
+		}
+		cells.shuffle(rng);
+		for &(col, row) in cells.iter().take(count - positions.len()) {
+			positions.push(Vec3::new(
+				min.x + (col as f32 + 0.5) * COIN_SPACING + rng.gen_range(-jitter..=jitter),
+				min.y + (row as f32 + 0.5) * COIN_SPACING + rng.gen_range(-jitter..=jitter),
+				// Leave a small gap so layers don't start out interpenetrating
+				base_z + (layer as f32 + 0.5) * COIN_THICKNESS * 1.2,
+			));
+		}
+	}
+	positions
+}
+
+pub fn fast_forward(mut time: ResMut<Time<Virtual>>) {
+	time.set_relative_speed(SETTLE_SPEED);
+	// Let each frame catch up on as many fixed ticks as it needs
+	time.set_max_delta(Duration::from_secs(2));
+}
+
+pub fn suppress_rendering(mut cams: Query<&mut Camera>) {
+	for mut cam in &mut cams {
+		if cam.is_active {
+			cam.is_active = false;
+		}
+	}
+}
+
+pub fn check_settled(
+	coins: Query<(&LinearVelocity, &AngularVelocity), With<Prefilled>>,
+	t: Res<Time>,
+	mut quiet_ticks: Local<u32>,
+	mut next_state: ResMut<NextState<GameState>>,
+) {
+	let quiet = coins
+		.iter()
+		.all(|(lin, ang)| lin.length() < 0.1 && ang.length() < 0.2);
+	*quiet_ticks = if quiet { *quiet_ticks + 1 } else { 0 };
+	if *quiet_ticks >= SETTLED_TICKS {
+		info!(secs = t.elapsed_secs(), "Bed settled");
+		next_state.set(GameState::Playing);
+	} else if t.elapsed_secs() > MAX_SETTLE_SECS {
+		warn!("Bed didn't settle in {MAX_SETTLE_SECS}s, starting anyway");
+		next_state.set(GameState::Playing);
+	}
+}
+
+pub fn finish_settling(
+	mut cams: Query<&mut Camera>,
+	mut time: ResMut<Time<Virtual>>,
+	mut coins: Query<(&mut Prefilled, &GlobalTransform)>,
+) {
+	for mut cam in &mut cams {
+		cam.is_active = true;
+	}
+	time.set_relative_speed(1.0);
+	time.set_max_delta(Duration::from_millis(250));
+	for (mut prefilled, xform) in &mut coins {
+		prefilled.origin = xform.translation();
+	}
+}
+
+pub fn check_moved(mut cmds: Commands, coins: Query<(Entity, &Prefilled, &GlobalTransform)>) {
+	for (id, prefilled, xform) in &coins {
+		if xform.translation().distance(prefilled.origin) > MOVED_DISTANCE {
+			cmds.entity(id).remove::<Prefilled>();
+		}
+	}
+}
@@ -0,0 +1,171 @@
+use crate::machine::{collect, MachineConfig};
+use crate::Winnings;
+use avian3d::prelude::{Collider, Restitution, RigidBody};
+use bevy::prelude::*;
+use currency::Currency;
+use std::collections::BTreeMap;
+use std::f32::consts::FRAC_PI_2;
+use std::fmt;
+
+/// Things other than coins sitting on the platform, pushed off and collected just like
+/// coins but kept in a [`PrizeInventory`]. Placed from [`MachineConfig::prizes`].
+pub struct PrizesPlugin;
+
+impl Plugin for PrizesPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<PrizeInventory>()
+			.add_event::<PrizeCollected>()
+			.add_systems(Startup, spawn_prizes)
+			.add_systems(Update, award_prizes.after(collect));
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PrizeKind {
+	/// A big, thick coin.
+	Token,
+	/// A cube, which slides rather than rolls.
+	Box,
+	/// Rolls around a lot more than anything else on the platform.
+	Ball,
+}
+
+impl PrizeKind {
+	pub fn collider(self) -> Collider {
+		match self {
+			Self::Token => Collider::cylinder(1.5, 0.5),
+			Self::Box => Collider::cuboid(2.0, 2.0, 2.0),
+			Self::Ball => Collider::sphere(1.0),
+		}
+	}
+
+	pub fn mesh(self) -> Mesh {
+		match self {
+			Self::Token => Cylinder::new(1.5, 0.5).into(),
+			Self::Box => Cuboid::new(2.0, 2.0, 2.0).into(),
+			Self::Ball => Sphere::new(1.0).into(),
+		}
+	}
+
+	pub fn color(self) -> Color {
+		match self {
+			Self::Token => Color::linear_rgb(0.8, 0.1, 0.1),
+			Self::Box => Color::linear_rgb(0.1, 0.3, 0.9),
+			Self::Ball => Color::linear_rgb(0.1, 0.8, 0.2),
+		}
+	}
+}
+
+impl fmt::Display for PrizeKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Token => "Token",
+			Self::Box => "Box",
+			Self::Ball => "Ball",
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PrizeConfig {
+	pub kind: PrizeKind,
+	pub value: Currency,
+	/// Where it's dropped at startup, above the platform so it lands on any pre-filled coins.
+	pub position: Vec3,
+}
+
+#[derive(Component, Debug, Clone)]
+#[require(RigidBody, Collider, Mesh3d, MeshMaterial3d<StandardMaterial>)]
+pub struct Prize {
+	pub kind: PrizeKind,
+	pub value: Currency,
+}
+
+/// Sent by [`collect`] when a [`Prize`] leaves the platform.
+#[derive(Event, Debug, Clone)]
+pub struct PrizeCollected {
+	pub kind: PrizeKind,
+	pub value: Currency,
+}
+
+/// Every prize won so far. Their value has been added to [`Winnings`] too.
+#[derive(Resource, Debug, Clone)]
+pub struct PrizeInventory {
+	pub counts: BTreeMap<PrizeKind, u32>,
+	pub value: Currency,
+}
+
+impl Default for PrizeInventory {
+	fn default() -> Self {
+		Self {
+			counts: BTreeMap::new(),
+			value: Currency::from_str("$0.00").unwrap(),
+		}
+	}
+}
+
+impl PrizeInventory {
+	pub fn total(&self) -> u32 {
+		self.counts.values().sum()
+	}
+}
+
+/// e.g. `2 Token, 1 Ball ($7.00)`, or `none`.
+impl fmt::Display for PrizeInventory {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.total() == 0 {
+			return f.write_str("none");
+		}
+		for (i, (kind, count)) in self.counts.iter().enumerate() {
+			if i > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{count} {kind}")?;
+		}
+		write!(f, " ({})", self.value)
+	}
+}
+
+pub fn spawn_prizes(
+	mut cmds: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut mats: ResMut<Assets<StandardMaterial>>,
+	config: Res<MachineConfig>,
+) {
+	for prize in &config.prizes {
+		let rotation = match prize.kind {
+			// Lying flat, like the coins
+			PrizeKind::Token => Quat::from_rotation_x(FRAC_PI_2),
+			_ => Quat::IDENTITY,
+		};
+		cmds.spawn((
+			Prize {
+				kind: prize.kind,
+				value: prize.value.clone(),
+			},
+			Name::new(format!("{} prize", prize.kind)),
+			prize.kind.collider(),
+			Mesh3d(meshes.add(prize.kind.mesh())),
+			MeshMaterial3d(mats.add(StandardMaterial {
+				base_color: prize.kind.color(),
+				perceptual_roughness: 0.4,
+				..default()
+			})),
+			Transform::from_translation(prize.position).with_rotation(rotation),
+			Restitution::new(0.3),
+		));
+	}
+}
+
+pub fn award_prizes(
+	mut events: EventReader<PrizeCollected>,
+	mut inventory: ResMut<PrizeInventory>,
+	mut winnings: ResMut<Winnings>,
+) {
+	for ev in events.read() {
+		info!(kind = %ev.kind, value = %ev.value, "Won a prize");
+		winnings.0 = winnings.0.clone() + ev.value.clone();
+		*inventory.counts.entry(ev.kind).or_default() += 1;
+		inventory.value = inventory.value.clone() + ev.value.clone();
+	}
+}
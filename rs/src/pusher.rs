@@ -0,0 +1,439 @@
+use crate::coins::Coin;
+use avian3d::collision::{ColliderAabb, Collisions};
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+use std::str::FromStr;
+
+/// Keyframed motion for a [`crate::machine::Piston`], looping from the last keyframe
+/// back to the first.
+#[derive(Debug, Clone)]
+pub struct PusherMotion {
+	pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+	pub translation: Vec3,
+	pub rotation: Quat,
+	/// Seconds to stay at this keyframe before moving on.
+	pub dwell: f32,
+	/// Seconds spent moving from this keyframe to the next one.
+	pub duration: f32,
+	/// Easing of the move to the next keyframe.
+	pub ease: EaseFunction,
+}
+
+impl Keyframe {
+	pub fn new(translation: Vec3, duration: f32) -> Self {
+		Self {
+			translation,
+			rotation: Quat::IDENTITY,
+			dwell: 0.0,
+			duration,
+			ease: EaseFunction::SineInOut,
+		}
+	}
+
+	pub fn with_rotation(self, rotation: Quat) -> Self {
+		Self { rotation, ..self }
+	}
+
+	pub fn with_dwell(self, dwell: f32) -> Self {
+		Self { dwell, ..self }
+	}
+
+	pub fn with_ease(self, ease: EaseFunction) -> Self {
+		Self { ease, ..self }
+	}
+}
+
+impl PusherMotion {
+	/// Back and forth between `start` and `end`, taking `duration` seconds each way
+	/// and pausing for `dwell` at both ends.
+	pub fn linear(start: Vec3, end: Vec3, duration: f32, dwell: f32) -> Self {
+		Self {
+			keyframes: vec![
+				Keyframe::new(start, duration).with_dwell(dwell),
+				Keyframe::new(end, duration).with_dwell(dwell),
+			],
+		}
+	}
+
+	/// Side to side across `width`, centered on `center`, like a bulldozer blade.
+	pub fn sweeper(center: Vec3, width: f32, duration: f32, dwell: f32) -> Self {
+		let offset = Vec3::X * 0.5 * width;
+		Self::linear(center - offset, center + offset, duration, dwell)
+	}
+
+	/// One full turn about the vertical axis at a constant rate, every `period` seconds.
+	pub fn turntable(center: Vec3, period: f32) -> Self {
+		Self::spinner(center, Vec3::Z, period)
+	}
+
+	/// One full turn about `axis` at a constant rate, every `period` seconds.
+	/// Negative periods turn the other way.
+	pub fn spinner(center: Vec3, axis: Vec3, period: f32) -> Self {
+		// Each segment must turn less than half a revolution to interpolate the right way around
+		let segments = 3;
+		let axis = axis.normalize_or(Vec3::Z) * period.signum();
+		Self {
+			keyframes: (0..segments)
+				.map(|i| {
+					Keyframe::new(center, period.abs() / segments as f32)
+						.with_rotation(Quat::from_axis_angle(
+							axis,
+							TAU * i as f32 / segments as f32,
+						))
+						.with_ease(EaseFunction::Linear)
+				})
+				.collect(),
+		}
+	}
+
+	/// Stretches how far the motion moves away from its first keyframe by `factor`, keeping
+	/// its shape and timing.
+	pub fn scale_stroke(&mut self, factor: f32) {
+		let Some(origin) = self.keyframes.first().map(|kf| kf.translation) else {
+			return;
+		};
+		for kf in &mut self.keyframes {
+			kf.translation = origin + (kf.translation - origin) * factor;
+		}
+	}
+
+	/// Seconds to go through every keyframe once.
+	pub fn period(&self) -> f32 {
+		self.keyframes.iter().map(|kf| kf.dwell + kf.duration).sum()
+	}
+
+	/// Translation and rotation `t` seconds into the motion.
+	pub fn sample(&self, t: f32) -> Option<(Vec3, Quat)> {
+		let period = self.period();
+		if self.keyframes.is_empty() || period <= 0.0 || !t.is_finite() {
+			return None;
+		}
+		let mut t = t.rem_euclid(period);
+		for (i, kf) in self.keyframes.iter().enumerate() {
+			if t < kf.dwell {
+				return Some((kf.translation, kf.rotation));
+			}
+			t -= kf.dwell;
+			if t < kf.duration {
+				let next = &self.keyframes[(i + 1) % self.keyframes.len()];
+				let s = t / kf.duration;
+				return Some((
+					EasingCurve::new(kf.translation, next.translation, kf.ease).sample_clamped(s),
+					EasingCurve::new(kf.rotation, next.rotation, kf.ease).sample_clamped(s),
+				));
+			}
+			t -= kf.duration;
+		}
+		// Only reachable through rounding error at the very end of the period
+		let first = &self.keyframes[0];
+		Some((first.translation, first.rotation))
+	}
+
+	/// Where a kinematic body following this motion in `frame` should be at `t`, and the
+	/// velocities that take it to where it should be at `next`, `dt` seconds later.
+	/// Velocity is needed, not just position, for friction to move coins.
+	pub fn step(&self, frame: &Transform, t: f32, next: f32, dt: f32) -> Option<Step> {
+		let (a, rot_a) = self.sample(t)?;
+		let (b, rot_b) = self.sample(next)?;
+		let (a, b) = (frame.transform_point(a), frame.transform_point(b));
+		let (rot_a, rot_b) = (frame.rotation * rot_a, frame.rotation * rot_b);
+		let (axis, angle) = (rot_b * rot_a.inverse()).to_axis_angle();
+		// Take the short way around
+		let angle = if angle > PI { angle - TAU } else { angle };
+		Some(Step {
+			translation: a,
+			rotation: rot_a,
+			linear_velocity: (b - a) / dt,
+			angular_velocity: axis * angle / dt,
+		})
+	}
+}
+
+/// See [`PusherMotion::step`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Step {
+	pub translation: Vec3,
+	pub rotation: Quat,
+	pub linear_velocity: Vec3,
+	pub angular_velocity: Vec3,
+}
+
+/// Limits how hard a [`crate::machine::Piston`] pushes. While coins push back harder
+/// than `max_force` the piston holds still, and if that adds up to `jam_after` seconds it
+/// counts as jammed: [`PusherJammed`] is sent and it tries to free itself with
+/// `recovery`. The motor takes [`MOTOR_RAMP`] seconds to get up to speed or stop, rather
+/// than changing direction from one tick to the next.
+#[derive(Component, Debug, Clone)]
+pub struct ForceLimit {
+	pub max_force: f32,
+	pub jam_after: f32,
+	pub recovery: JamRecovery,
+	state: MotorState,
+	/// How fast the motor is going, from -1.0 (full speed backward) to 1.0.
+	motor: f32,
+}
+
+/// Seconds a [`ForceLimit`]ed piston's motor takes to go from stopped to full speed.
+pub const MOTOR_RAMP: f32 = 0.25;
+
+impl ForceLimit {
+	pub fn new(max_force: f32, jam_after: f32, recovery: JamRecovery) -> Self {
+		Self {
+			max_force,
+			jam_after,
+			recovery,
+			state: MotorState::Running { stalled: 0.0 },
+			motor: 1.0,
+		}
+	}
+
+	pub fn is_jammed(&self) -> bool {
+		matches!(self.state, MotorState::Recovering { .. })
+	}
+
+	/// How fast to play the motion this tick, given how hard coins pushed back on the
+	/// last one: 1.0 forward, 0.0 hold, -1.0 backward, easing between them. Also returns
+	/// whether it just jammed.
+	pub fn update(&mut self, force: f32, dt: f32) -> (f32, bool) {
+		let (target, just_jammed) = self.target(force, dt);
+		let step = dt / MOTOR_RAMP;
+		self.motor += (target - self.motor).clamp(-step, step);
+		(self.motor, just_jammed)
+	}
+
+	/// Which way the motor should be going, and whether it just jammed.
+	fn target(&mut self, force: f32, dt: f32) -> (f32, bool) {
+		match &mut self.state {
+			MotorState::Running { stalled } => {
+				if force <= self.max_force {
+					// Forget stalls slowly so that pushing in fits and starts still adds up
+					*stalled = (*stalled - 0.5 * dt).max(0.0);
+					return (1.0, false);
+				}
+				*stalled += dt;
+				if *stalled < self.jam_after {
+					return (0.0, false);
+				}
+				let remaining = match self.recovery {
+					JamRecovery::Retry { delay } => delay,
+					JamRecovery::Reverse { duration } => duration,
+				};
+				self.state = MotorState::Recovering { remaining };
+				(0.0, true)
+			}
+			MotorState::Recovering { remaining } => {
+				*remaining -= dt;
+				if *remaining <= 0.0 {
+					self.state = MotorState::Running { stalled: 0.0 };
+					return (0.0, false);
+				}
+				match self.recovery {
+					JamRecovery::Retry { .. } => (0.0, false),
+					JamRecovery::Reverse { .. } => (-1.0, false),
+				}
+			}
+		}
+	}
+}
+
+/// What a [`ForceLimit`]ed piston does once it jams.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JamRecovery {
+	/// Wait `delay` seconds, then push again.
+	Retry { delay: f32 },
+	/// Back off for `duration` seconds, then push again.
+	Reverse { duration: f32 },
+}
+
+impl Default for JamRecovery {
+	fn default() -> Self {
+		Self::Reverse { duration: 1.0 }
+	}
+}
+
+/// `retry:<secs>` or `reverse:<secs>`.
+impl FromStr for JamRecovery {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, secs) = s
+			.split_once(':')
+			.ok_or_else(|| format!("Expected `retry:<secs>` or `reverse:<secs>`, got `{s}`"))?;
+		let secs = secs
+			.parse::<f32>()
+			.ok()
+			.filter(|secs| *secs > 0.0)
+			.ok_or_else(|| format!("Invalid recovery time `{secs}`"))?;
+		match name {
+			"retry" => Ok(Self::Retry { delay: secs }),
+			"reverse" => Ok(Self::Reverse { duration: secs }),
+			_ => Err(format!(
+				"Unknown jam recovery `{name}`, expected `retry` or `reverse`"
+			)),
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum MotorState {
+	/// `stalled` is roughly how many of the last seconds were spent held back.
+	Running {
+		stalled: f32,
+	},
+	Recovering {
+		remaining: f32,
+	},
+}
+
+/// Sent when a [`ForceLimit`]ed piston has been held back for too long.
+#[derive(Event, Debug, Clone)]
+pub struct PusherJammed {
+	pub piston: Entity,
+	/// How hard the coins were pushing back, in force units, averaged over the last physics step.
+	pub force: f32,
+}
+
+/// Force the coins touching the sides of the piston put on it during the last physics step.
+/// Coins resting on top only push down, so they aren't counted.
+pub fn resisting_force(
+	piston: Entity,
+	aabb: &ColliderAabb,
+	coins: &Query<&GlobalTransform, With<Coin>>,
+	collisions: &Collisions,
+	dt: f32,
+) -> f32 {
+	let impulse = collisions
+		.collisions_with_entity(piston)
+		.filter_map(|contacts| {
+			let other = if contacts.entity1 == piston {
+				contacts.entity2
+			} else {
+				contacts.entity1
+			};
+			let pos = coins.get(other).ok()?.translation();
+			(pos.z < aabb.max.z - 0.5).then_some(contacts.total_normal_impulse)
+		})
+		.sum::<f32>();
+	impulse / dt
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn back_and_forth() -> PusherMotion {
+		PusherMotion::linear(Vec3::ZERO, Vec3::new(0.0, -4.0, 0.0), 2.0, 0.5)
+	}
+
+	#[test]
+	fn period_covers_every_move_and_dwell() {
+		assert_eq!(back_and_forth().period(), 5.0);
+	}
+
+	#[test]
+	fn sample_dwells_then_eases_to_the_next_keyframe() {
+		let motion = back_and_forth();
+		assert_eq!(motion.sample(0.25), Some((Vec3::ZERO, Quat::IDENTITY)));
+		let (halfway, _) = motion.sample(1.5).unwrap();
+		assert!(halfway.abs_diff_eq(Vec3::new(0.0, -2.0, 0.0), 1e-5));
+		let (end, _) = motion.sample(2.75).unwrap();
+		assert!(end.abs_diff_eq(Vec3::new(0.0, -4.0, 0.0), 1e-5));
+	}
+
+	#[test]
+	fn sample_loops_back_to_the_first_keyframe() {
+		let motion = back_and_forth();
+		let (a, _) = motion.sample(1.2).unwrap();
+		let (b, _) = motion.sample(1.2 + 3.0 * motion.period()).unwrap();
+		assert!(a.abs_diff_eq(b, 1e-4));
+		let (c, _) = motion.sample(-0.2).unwrap();
+		let (d, _) = motion.sample(motion.period() - 0.2).unwrap();
+		assert!(c.abs_diff_eq(d, 1e-4));
+	}
+
+	#[test]
+	fn sample_needs_something_to_play() {
+		assert_eq!(PusherMotion { keyframes: vec![] }.sample(0.0), None);
+		assert_eq!(
+			PusherMotion::linear(Vec3::ZERO, Vec3::X, 0.0, 0.0).sample(0.0),
+			None
+		);
+		assert_eq!(back_and_forth().sample(f32::NAN), None);
+	}
+
+	#[test]
+	fn spinner_turns_at_a_constant_rate() {
+		let motion = PusherMotion::turntable(Vec3::ZERO, 4.0);
+		let (_, quarter) = motion.sample(1.0).unwrap();
+		assert!((quarter * Vec3::X).abs_diff_eq(Vec3::Y, 1e-5));
+		let (_, three_quarters) = motion.sample(3.0).unwrap();
+		assert!((three_quarters * Vec3::X).abs_diff_eq(Vec3::NEG_Y, 1e-5));
+	}
+
+	#[test]
+	fn step_moves_toward_the_next_sample() {
+		let motion = back_and_forth();
+		let step = motion.step(&Transform::IDENTITY, 1.0, 1.1, 0.1).unwrap();
+		let (next, _) = motion.sample(1.1).unwrap();
+		assert!((step.translation + step.linear_velocity * 0.1).abs_diff_eq(next, 1e-4));
+		assert_eq!(step.angular_velocity, Vec3::ZERO);
+	}
+
+	#[test]
+	fn scale_stroke_keeps_the_first_keyframe() {
+		let mut motion = PusherMotion::linear(Vec3::ONE, Vec3::new(1.0, -3.0, 1.0), 1.0, 0.0);
+		motion.scale_stroke(1.5);
+		assert_eq!(motion.keyframes[0].translation, Vec3::ONE);
+		assert_eq!(motion.keyframes[1].translation, Vec3::new(1.0, -5.0, 1.0));
+	}
+
+	#[test]
+	fn force_limit_eases_to_a_stop_then_backs_off() {
+		let mut limit = ForceLimit::new(10.0, 0.5, JamRecovery::Reverse { duration: 0.5 });
+		let dt = 0.125;
+		assert_eq!(limit.update(0.0, dt), (1.0, false));
+		assert_eq!(limit.update(20.0, dt), (0.5, false));
+		assert_eq!(limit.update(20.0, dt), (0.0, false));
+		assert_eq!(limit.update(20.0, dt), (0.0, false));
+		assert!(!limit.is_jammed());
+		assert_eq!(limit.update(20.0, dt), (0.0, true));
+		assert!(limit.is_jammed());
+		assert_eq!(limit.update(0.0, dt), (-0.5, false));
+		assert_eq!(limit.update(0.0, dt), (-1.0, false));
+		assert_eq!(limit.update(0.0, dt), (-1.0, false));
+		assert_eq!(limit.update(0.0, dt), (-0.5, false));
+		assert!(!limit.is_jammed());
+		assert_eq!(limit.update(0.0, dt), (0.0, false));
+		assert_eq!(limit.update(0.0, dt), (0.5, false));
+	}
+
+	#[test]
+	fn force_limit_retry_waits_in_place() {
+		let mut limit = ForceLimit::new(10.0, 0.25, JamRecovery::Retry { delay: 0.5 });
+		assert_eq!(limit.update(20.0, 0.25), (0.0, true));
+		assert_eq!(limit.update(0.0, 0.25), (0.0, false));
+		assert!(limit.is_jammed());
+		assert_eq!(limit.update(0.0, 0.25), (0.0, false));
+		assert!(!limit.is_jammed());
+		assert_eq!(limit.update(0.0, 0.25), (1.0, false));
+	}
+
+	#[test]
+	fn jam_recovery_parses() {
+		assert_eq!(
+			"retry:0.5".parse::<JamRecovery>(),
+			Ok(JamRecovery::Retry { delay: 0.5 })
+		);
+		assert_eq!(
+			"reverse:2".parse::<JamRecovery>(),
+			Ok(JamRecovery::Reverse { duration: 2.0 })
+		);
+		for bad in ["reverse", "reverse:-1", "reverse:soon", "spin:1"] {
+			assert!(bad.parse::<JamRecovery>().is_err(), "{bad}");
+		}
+	}
+}
@@ -0,0 +1,278 @@
+use crate::coins::CoinShower;
+use crate::machine::MachineConfig;
+use crate::pockets::{PocketHit, Reward};
+use crate::{cents, GameState, Winnings};
+use bevy::prelude::*;
+use currency::Currency;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+/// A slot machine with three reels, spun by coins passing a [`Reward::SpinReels`] pocket.
+/// Configured by [`crate::machine::MachineConfig::reels`].
+pub struct ReelsPlugin;
+
+impl Plugin for ReelsPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_event::<ReelResult>().add_systems(
+			FixedUpdate,
+			(queue_spins, spin_reels)
+				.chain()
+				.run_if(resource_exists::<Reels>.and(in_state(GameState::Playing))),
+		);
+	}
+}
+
+pub const REEL_COUNT: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct ReelConfig {
+	pub symbols: Vec<Symbol>,
+	/// Seconds the reels spin before stopping.
+	pub spin_secs: f32,
+	/// Most spins that can be saved up while the reels are busy.
+	pub max_stock: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+	pub name: String,
+	/// Relative chance of landing on each reel.
+	pub weight: u32,
+	/// What three of this symbol in a row pays.
+	pub payout: Option<ReelPayout>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReelPayout {
+	Credit(Currency),
+	Shower(usize),
+}
+
+impl Default for ReelConfig {
+	fn default() -> Self {
+		let symbol = |name: &str, weight, payout| Symbol {
+			name: name.into(),
+			weight,
+			payout,
+		};
+		let credit = |value: &str| Some(ReelPayout::Credit(Currency::from_str(value).unwrap()));
+		Self {
+			symbols: vec![
+				symbol("CHERRY", 8, credit("$2.00")),
+				symbol("BELL", 5, credit("$5.00")),
+				symbol("BAR", 3, Some(ReelPayout::Shower(20))),
+				symbol("7", 1, Some(ReelPayout::Shower(50))),
+				symbol("-", 10, None),
+			],
+			spin_secs: 2.0,
+			max_stock: 5,
+		}
+	}
+}
+
+impl ReelConfig {
+	fn total_weight(&self) -> u32 {
+		self.symbols.iter().map(|symbol| symbol.weight).sum()
+	}
+
+	/// Chance of each symbol coming up on all reels at once, by index.
+	pub fn odds(&self) -> Vec<f64> {
+		let total = self.total_weight().max(1) as f64;
+		self.symbols
+			.iter()
+			.map(|symbol| (symbol.weight as f64 / total).powi(REEL_COUNT as i32))
+			.collect()
+	}
+
+	/// Average credit per spin, in cents, not counting showers.
+	pub fn expected_credit_cents(&self) -> f64 {
+		self.symbols
+			.iter()
+			.zip(self.odds())
+			.map(|(symbol, odds)| match &symbol.payout {
+				Some(ReelPayout::Credit(value)) => odds * cents(value) as f64,
+				_ => 0.0,
+			})
+			.sum()
+	}
+
+	/// Average coins showered per spin.
+	pub fn expected_shower_coins(&self) -> f64 {
+		self.symbols
+			.iter()
+			.zip(self.odds())
+			.map(|(symbol, odds)| match symbol.payout {
+				Some(ReelPayout::Shower(count)) => odds * count as f64,
+				_ => 0.0,
+			})
+			.sum()
+	}
+
+	fn roll(&self, rng: &mut StdRng) -> usize {
+		let mut roll = rng.gen_range(0..self.total_weight().max(1));
+		for (i, symbol) in self.symbols.iter().enumerate() {
+			if roll < symbol.weight {
+				return i;
+			}
+			roll -= symbol.weight;
+		}
+		0
+	}
+}
+
+/// Table of each symbol's odds and payout.
+impl fmt::Display for ReelConfig {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (symbol, odds) in self.symbols.iter().zip(self.odds()) {
+			let payout = match &symbol.payout {
+				Some(ReelPayout::Credit(value)) => value.to_string(),
+				Some(ReelPayout::Shower(count)) => format!("{count} coin shower"),
+				None => "nothing".into(),
+			};
+			writeln!(
+				f,
+				"  {:<8} 1 in {:<8.0} {payout}",
+				symbol.name,
+				1.0 / odds.max(f64::MIN_POSITIVE)
+			)?;
+		}
+		write!(
+			f,
+			"  Per spin: {:.1}c credit, {:.2} shower coins",
+			self.expected_credit_cents(),
+			self.expected_shower_coins()
+		)
+	}
+}
+
+#[derive(Resource, Debug)]
+pub struct Reels {
+	pub config: ReelConfig,
+	/// Spins waiting for the reels to stop.
+	pub stock: u32,
+	/// Seconds left on the current spin, if the reels are spinning.
+	pub spinning: Option<f32>,
+	/// Symbol showing on each reel, by index into [`ReelConfig::symbols`].
+	pub showing: [usize; REEL_COUNT],
+	pub spins: u64,
+	rng: StdRng,
+}
+
+impl Reels {
+	/// Fails if no symbol can ever come up.
+	pub fn new(config: ReelConfig, seed: u64) -> Result<Self, String> {
+		if config.total_weight() == 0 {
+			return Err("Reels need at least one symbol with a weight above zero".into());
+		}
+		Ok(Self {
+			config,
+			stock: 0,
+			spinning: None,
+			showing: [0; REEL_COUNT],
+			spins: 0,
+			rng: StdRng::seed_from_u64(seed),
+		})
+	}
+}
+
+/// Sent when the reels stop. Any payout has already been made.
+#[derive(Event, Debug, Clone)]
+pub struct ReelResult {
+	pub symbols: [usize; REEL_COUNT],
+	pub payout: Option<ReelPayout>,
+}
+
+pub fn queue_spins(mut reels: ResMut<Reels>, mut hits: EventReader<PocketHit>) {
+	for hit in hits.read() {
+		if !matches!(hit.reward, Reward::SpinReels) {
+			continue;
+		}
+		if reels.stock < reels.config.max_stock {
+			reels.stock += 1;
+		}
+	}
+}
+
+pub fn spin_reels(
+	mut reels: ResMut<Reels>,
+	mut results: EventWriter<ReelResult>,
+	mut winnings: ResMut<Winnings>,
+	mut showers: EventWriter<CoinShower>,
+	config: Res<MachineConfig>,
+	t: Res<Time>,
+) {
+	let reels = &mut *reels;
+	let Some(remaining) = &mut reels.spinning else {
+		if reels.stock > 0 {
+			reels.stock -= 1;
+			reels.spinning = Some(reels.config.spin_secs);
+		}
+		return;
+	};
+	*remaining -= t.delta_secs();
+	if *remaining > 0.0 {
+		return;
+	}
+	reels.spinning = None;
+	reels.spins += 1;
+
+	let symbols = [(); REEL_COUNT].map(|_| reels.config.roll(&mut reels.rng));
+	reels.showing = symbols;
+	let payout = if symbols.iter().all(|&symbol| symbol == symbols[0]) {
+		reels.config.symbols[symbols[0]].payout.clone()
+	} else {
+		None
+	};
+	match &payout {
+		Some(ReelPayout::Credit(value)) => {
+			winnings.0 = winnings.0.clone() + value.clone();
+		}
+		&Some(ReelPayout::Shower(count)) => {
+			showers.send(CoinShower::new(count, config.coin_value.clone()));
+		}
+		None => {}
+	}
+	let names = symbols.map(|symbol| reels.config.symbols[symbol].name.as_str());
+	info!(?names, ?payout, "Reels stopped");
+	results.send(ReelResult { symbols, payout });
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config(weights: &[u32]) -> ReelConfig {
+		ReelConfig {
+			symbols: weights
+				.iter()
+				.map(|&weight| Symbol {
+					name: weight.to_string(),
+					weight,
+					payout: Some(ReelPayout::Credit(Currency::from_str("$2.00").unwrap())),
+				})
+				.collect(),
+			..default()
+		}
+	}
+
+	#[test]
+	fn odds_are_each_weight_share_on_every_reel() {
+		let odds = config(&[1, 3]).odds();
+		assert!((odds[0] - 1.0 / 64.0).abs() < 1e-12);
+		assert!((odds[1] - 27.0 / 64.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn expected_credit_weighs_payouts_by_odds() {
+		let credit = config(&[1, 3]).expected_credit_cents();
+		assert!((credit - 200.0 * 28.0 / 64.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn reels_need_something_to_land_on() {
+		assert!(Reels::new(config(&[]), 0).is_err());
+		assert!(Reels::new(config(&[0, 0]), 0).is_err());
+		assert!(Reels::new(config(&[0, 1]), 0).is_ok());
+	}
+}
@@ -0,0 +1,410 @@
+use crate::coins::{
+	drop_coins, ActiveChute, AutoDrop, AutoDropChutes, AutoDropTimer, Coin, CoinDropReason,
+	DropCoin,
+};
+use crate::hinges::{flip, Flip};
+use crate::nudge::{nudge, Nudge};
+use crate::power_ups::{buy_power_ups, BuyPowerUp};
+use crate::strategy::run_strategy;
+use crate::{cents, from_cents, GameState};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Records the player's inputs, by fixed tick, and plays them back.
+///
+/// Covers manual [`DropCoin`]s, [`Flip`]s, [`Nudge`]s and [`BuyPowerUp`]s, and changes to
+/// [`AutoDrop`], the [`AutoDropTimer`], the [`ActiveChute`] and [`AutoDropChutes`].
+/// What auto-drop and strategies drop isn't recorded. The seed goes in the file's header
+/// and is used again on playback, so they make the same decisions as long as the same
+/// program and strategy are picked.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Replay>().add_systems(
+			FixedUpdate,
+			(play_inputs, record_inputs)
+				.chain()
+				.before(drop_coins)
+				.before(flip)
+				.before(nudge)
+				.before(buy_power_ups)
+				// Strategy drops aren't player input
+				.before(run_strategy)
+				.run_if(in_state(GameState::Playing)),
+		);
+	}
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Replay {
+	/// Fixed ticks since play started.
+	pub tick: u64,
+	/// Where inputs are appended as they happen.
+	pub recording: Option<PathBuf>,
+	/// Inputs still to be played back, in order.
+	pub playback: VecDeque<(u64, ReplayInput)>,
+	/// What the run was seeded with, from the header. Older recordings don't have one.
+	pub seed: Option<u64>,
+}
+
+impl Replay {
+	/// Starts a new recording at `path`, replacing whatever was there. `seed` goes in the
+	/// header, so playback can roll the same numbers.
+	pub fn record(path: PathBuf, seed: u64) -> io::Result<Self> {
+		fs::write(&path, format!("seed,{seed}\n"))?;
+		Ok(Self {
+			recording: Some(path),
+			seed: Some(seed),
+			..default()
+		})
+	}
+
+	/// Loads a recording made with [`Replay::record`] to play back.
+	pub fn load(path: PathBuf) -> io::Result<Self> {
+		let saved = fs::read_to_string(&path)?;
+		let mut lines = saved.lines().filter(|line| !line.is_empty()).peekable();
+		let seed = match lines.peek().and_then(|line| line.strip_prefix("seed,")) {
+			Some(seed) => {
+				let seed = seed.parse().map_err(|_| invalid(lines.peek().unwrap()))?;
+				lines.next();
+				Some(seed)
+			}
+			None => None,
+		};
+		let playback = lines
+			.map(|line| {
+				let (tick, input) = line.split_once(',').unwrap_or((line, ""));
+				Ok((
+					tick.parse().map_err(|_| invalid(line))?,
+					input.parse().map_err(|_| invalid(line))?,
+				))
+			})
+			.collect::<io::Result<_>>()?;
+		Ok(Self {
+			playback,
+			seed,
+			..default()
+		})
+	}
+}
+
+fn invalid(line: &str) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		format!("Invalid replay line `{line}`"),
+	)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReplayInput {
+	Drop {
+		value_cents: i64,
+		chute: Option<usize>,
+		aim: Option<f32>,
+	},
+	Flip(usize),
+	Nudge(Vec2),
+	BuyPowerUp(usize),
+	AutoDrop(bool),
+	AutoInterval(Duration),
+	Chute(usize),
+	AutoChutes(AutoDropChutes),
+}
+
+/// One line of a replay file, without the tick, e.g. `drop,100,1,-0.5`, `flip,2`,
+/// `nudge,0,-1`, `power-up,1`, `auto,true`, `auto-interval,500000000`, `chute,3` or
+/// `auto-chutes,cycle`.
+/// An empty chute or aim means `None`. Intervals are in nanoseconds, so the ones random
+/// auto-drop picks come back exactly.
+impl fmt::Display for ReplayInput {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Drop {
+				value_cents,
+				chute,
+				aim,
+			} => {
+				write!(f, "drop,{value_cents},")?;
+				if let Some(chute) = chute {
+					write!(f, "{chute}")?;
+				}
+				write!(f, ",")?;
+				if let Some(aim) = aim {
+					write!(f, "{aim}")?;
+				}
+				Ok(())
+			}
+			Self::Flip(index) => write!(f, "flip,{index}"),
+			Self::Nudge(direction) => write!(f, "nudge,{},{}", direction.x, direction.y),
+			Self::BuyPowerUp(index) => write!(f, "power-up,{index}"),
+			Self::AutoDrop(on) => write!(f, "auto,{on}"),
+			Self::AutoInterval(interval) => write!(f, "auto-interval,{}", interval.as_nanos()),
+			Self::Chute(index) => write!(f, "chute,{index}"),
+			Self::AutoChutes(AutoDropChutes::Active) => write!(f, "auto-chutes,active"),
+			Self::AutoChutes(AutoDropChutes::Cycle) => write!(f, "auto-chutes,cycle"),
+		}
+	}
+}
+
+impl FromStr for ReplayInput {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		fn optional<T: FromStr>(s: Option<&str>) -> Result<Option<T>, ()> {
+			match s {
+				None | Some("") => Ok(None),
+				Some(s) => s.parse().map(Some).map_err(|_| ()),
+			}
+		}
+		let mut parts = s.split(',');
+		match parts.next() {
+			Some("drop") => Ok(Self::Drop {
+				value_cents: parts.next().ok_or(())?.parse().map_err(|_| ())?,
+				chute: optional(parts.next())?,
+				aim: optional(parts.next())?,
+			}),
+			Some("flip") => Ok(Self::Flip(parts.next().ok_or(())?.parse().map_err(|_| ())?)),
+			Some("nudge") => Ok(Self::Nudge(Vec2::new(
+				parts.next().ok_or(())?.parse().map_err(|_| ())?,
+				parts.next().ok_or(())?.parse().map_err(|_| ())?,
+			))),
+			Some("power-up") => Ok(Self::BuyPowerUp(
+				parts.next().ok_or(())?.parse().map_err(|_| ())?,
+			)),
+			Some("auto") => Ok(Self::AutoDrop(
+				parts.next().ok_or(())?.parse().map_err(|_| ())?,
+			)),
+			Some("auto-interval") => Ok(Self::AutoInterval(Duration::from_nanos(
+				parts.next().ok_or(())?.parse().map_err(|_| ())?,
+			))),
+			Some("chute") => Ok(Self::Chute(
+				parts.next().ok_or(())?.parse().map_err(|_| ())?,
+			)),
+			Some("auto-chutes") => match parts.next() {
+				Some("active") => Ok(Self::AutoChutes(AutoDropChutes::Active)),
+				Some("cycle") => Ok(Self::AutoChutes(AutoDropChutes::Cycle)),
+				_ => Err(()),
+			},
+			_ => Err(()),
+		}
+	}
+}
+
+pub fn play_inputs(
+	mut replay: ResMut<Replay>,
+	mut drops: EventWriter<DropCoin>,
+	mut flips: EventWriter<Flip>,
+	mut nudges: EventWriter<Nudge>,
+	mut power_ups: EventWriter<BuyPowerUp>,
+	mut auto: ResMut<AutoDrop>,
+	mut timer: ResMut<AutoDropTimer>,
+	mut chute: ResMut<ActiveChute>,
+	mut auto_chutes: ResMut<AutoDropChutes>,
+) {
+	while replay
+		.playback
+		.front()
+		.is_some_and(|&(tick, _)| tick <= replay.tick)
+	{
+		let Some((_, input)) = replay.playback.pop_front() else {
+			break;
+		};
+		match input {
+			ReplayInput::Drop {
+				value_cents,
+				chute,
+				aim,
+			} => {
+				drops.send(DropCoin {
+					coin: Coin {
+						value: from_cents(value_cents),
+					},
+					reason: CoinDropReason::Manual,
+					chute,
+					aim,
+				});
+			}
+			ReplayInput::Flip(index) => {
+				flips.send(Flip(index));
+			}
+			ReplayInput::Nudge(direction) => {
+				nudges.send(Nudge(direction));
+			}
+			ReplayInput::BuyPowerUp(index) => {
+				power_ups.send(BuyPowerUp(index));
+			}
+			// Only when it changes, since that's what starts a new auto-drop run
+			ReplayInput::AutoDrop(on) => {
+				if **auto != on {
+					**auto = on;
+				}
+			}
+			ReplayInput::AutoInterval(interval) => {
+				timer.set_duration(interval);
+			}
+			ReplayInput::Chute(index) => {
+				chute.set_if_neq(ActiveChute(index));
+			}
+			ReplayInput::AutoChutes(mode) => {
+				auto_chutes.set_if_neq(mode);
+			}
+		}
+	}
+}
+
+pub fn record_inputs(
+	mut replay: ResMut<Replay>,
+	mut drops: EventReader<DropCoin>,
+	mut flips: EventReader<Flip>,
+	mut nudges: EventReader<Nudge>,
+	mut power_ups: EventReader<BuyPowerUp>,
+	auto: Res<AutoDrop>,
+	timer: Res<AutoDropTimer>,
+	chute: Res<ActiveChute>,
+	auto_chutes: Res<AutoDropChutes>,
+	mut last_settings: Local<Vec<ReplayInput>>,
+) {
+	// The player changes these directly rather than through events. All of them go in on
+	// the first tick, since a restored session can start them off as anything.
+	let settings = vec![
+		ReplayInput::AutoDrop(**auto),
+		ReplayInput::AutoInterval(timer.duration()),
+		ReplayInput::Chute(**chute),
+		ReplayInput::AutoChutes(*auto_chutes),
+	];
+	let changed = settings
+		.iter()
+		.enumerate()
+		.filter(|&(i, setting)| last_settings.get(i) != Some(setting))
+		.map(|(_, &setting)| setting)
+		.collect::<Vec<_>>();
+	*last_settings = settings;
+
+	let inputs = changed
+		.into_iter()
+		.chain(
+			drops
+				.read()
+				.filter(|ev| ev.reason == CoinDropReason::Manual)
+				.map(|ev| ReplayInput::Drop {
+					value_cents: cents(&ev.coin.value),
+					chute: ev.chute,
+					aim: ev.aim,
+				}),
+		)
+		.chain(flips.read().map(|&Flip(index)| ReplayInput::Flip(index)))
+		.chain(
+			nudges
+				.read()
+				.map(|&Nudge(direction)| ReplayInput::Nudge(direction)),
+		)
+		.chain(
+			power_ups
+				.read()
+				.map(|&BuyPowerUp(index)| ReplayInput::BuyPowerUp(index)),
+		)
+		.collect::<Vec<_>>();
+	let tick = replay.tick;
+	replay.tick += 1;
+	let Some(path) = &replay.recording else {
+		return;
+	};
+	if inputs.is_empty() {
+		return;
+	}
+	let lines = inputs
+		.iter()
+		.map(|input| format!("{tick},{input}\n"))
+		.collect::<String>();
+	let result = OpenOptions::new()
+		.append(true)
+		.open(path)
+		.and_then(|mut file| file.write_all(lines.as_bytes()));
+	if let Err(e) = result {
+		error!(?path, "Failed to write replay: {e}");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn round_trips(input: ReplayInput) {
+		assert_eq!(input.to_string().parse::<ReplayInput>(), Ok(input));
+	}
+
+	#[test]
+	fn inputs_round_trip() {
+		round_trips(ReplayInput::Drop {
+			value_cents: 100,
+			chute: Some(1),
+			aim: Some(-0.5),
+		});
+		round_trips(ReplayInput::Drop {
+			value_cents: 250,
+			chute: None,
+			aim: None,
+		});
+		round_trips(ReplayInput::Flip(2));
+		round_trips(ReplayInput::AutoDrop(true));
+		round_trips(ReplayInput::AutoInterval(Duration::from_secs_f32(1.37)));
+		round_trips(ReplayInput::Chute(3));
+		round_trips(ReplayInput::AutoChutes(AutoDropChutes::Cycle));
+	}
+
+	#[test]
+	fn nudges_round_trip() {
+		round_trips(ReplayInput::Nudge(Vec2::new(0.3, -1.0)));
+		assert!("nudge,1".parse::<ReplayInput>().is_err());
+	}
+
+	#[test]
+	fn power_up_purchases_round_trip() {
+		round_trips(ReplayInput::BuyPowerUp(1));
+		assert!("power-up,first".parse::<ReplayInput>().is_err());
+	}
+
+	#[test]
+	fn empty_chute_and_aim_are_none() {
+		assert_eq!(
+			"drop,100,,".parse::<ReplayInput>(),
+			Ok(ReplayInput::Drop {
+				value_cents: 100,
+				chute: None,
+				aim: None,
+			})
+		);
+	}
+
+	#[test]
+	fn bad_inputs_are_rejected() {
+		for line in ["", "drop", "flip,x", "auto-chutes,sideways", "teleport,1"] {
+			assert!(line.parse::<ReplayInput>().is_err(), "{line}");
+		}
+	}
+
+	#[test]
+	fn load_reads_the_seed_header() {
+		// Unique per process, so parallel test runs don't clash
+		let path = std::env::temp_dir().join(format!(
+			"coin-pusher-replay-seed-{}.txt",
+			std::process::id()
+		));
+		fs::write(&path, "seed,42\n0,flip,1\n3,auto,false\n").unwrap();
+		let replay = Replay::load(path.clone());
+		fs::remove_file(&path).ok();
+		let replay = replay.unwrap();
+		assert_eq!(replay.seed, Some(42));
+		assert_eq!(
+			replay.playback,
+			[(0, ReplayInput::Flip(1)), (3, ReplayInput::AutoDrop(false))]
+		);
+	}
+}
@@ -0,0 +1,226 @@
+use crate::coins::{drop_coins, shower_coins, Coin, CoinDropReason};
+use crate::machine::{collect, MachineConfig};
+use crate::{cents, from_cents, GameState};
+use bevy::prelude::*;
+use currency::Currency;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Rare coins that pay out more than their value, picked as they're dropped with the odds
+/// in [`MachineConfig::special_coins`]. Paid out by [`collect`].
+pub struct SpecialCoinsPlugin;
+
+impl Plugin for SpecialCoinsPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<CollectionBoost>()
+			.init_resource::<SpecialCoinRng>()
+			.add_systems(Startup, setup_special_coins)
+			.add_systems(
+				FixedUpdate,
+				pick_special_coins
+					.after(drop_coins)
+					.after(shower_coins)
+					.run_if(in_state(GameState::Playing)),
+			)
+			.add_systems(Update, tick_boost.before(collect));
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SpecialCoinConfig {
+	pub kind: SpecialKind,
+	/// Chance that any one dropped coin is this kind.
+	pub odds: f64,
+}
+
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub enum SpecialKind {
+	/// Pays `factor` times its value.
+	Golden { factor: u32 },
+	/// Multiplies everything collected in the next `secs` seconds by `factor`, itself included.
+	Multiplier { factor: f32, secs: f32 },
+}
+
+impl SpecialCoinConfig {
+	/// Picks at most one kind, so the odds in `configs` shouldn't add up to more than 1.
+	pub fn roll(configs: &[Self], rng: &mut impl Rng) -> Option<SpecialKind> {
+		let mut roll = rng.gen::<f64>();
+		for config in configs {
+			if roll < config.odds {
+				return Some(config.kind);
+			}
+			roll -= config.odds;
+		}
+		None
+	}
+}
+
+/// Rolls which coins are special. Seeded from the run's seed, since a single golden coin
+/// can swing a whole run.
+#[derive(Resource, Debug, Clone)]
+pub struct SpecialCoinRng(pub StdRng);
+
+impl SpecialCoinRng {
+	pub fn new(seed: u64) -> Self {
+		Self(StdRng::seed_from_u64(seed))
+	}
+}
+
+impl Default for SpecialCoinRng {
+	fn default() -> Self {
+		Self(StdRng::from_entropy())
+	}
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SpecialCoinAssets {
+	pub mesh: Handle<Mesh>,
+	pub golden: Handle<StandardMaterial>,
+	pub multiplier: Handle<StandardMaterial>,
+}
+
+/// Set by [`SpecialKind::Multiplier`], applied by [`collect`] until it runs out.
+#[derive(Resource, Debug, Clone)]
+pub struct CollectionBoost {
+	pub factor: f32,
+	pub secs: f32,
+}
+
+impl Default for CollectionBoost {
+	fn default() -> Self {
+		Self {
+			factor: 1.0,
+			secs: 0.0,
+		}
+	}
+}
+
+impl CollectionBoost {
+	pub fn is_active(&self) -> bool {
+		self.secs > 0.0
+	}
+
+	pub fn apply(&self, value: &Currency) -> Currency {
+		if !self.is_active() {
+			return value.clone();
+		}
+		from_cents((cents(value) as f32 * self.factor).round() as i64)
+	}
+
+	/// Starts a boost, or extends the current one if it's at least as big.
+	pub fn start(&mut self, factor: f32, secs: f32) {
+		if self.is_active() && self.factor > factor {
+			return;
+		}
+		self.secs = if self.factor == factor {
+			self.secs.max(secs)
+		} else {
+			secs
+		};
+		self.factor = factor;
+	}
+}
+
+pub fn setup_special_coins(
+	mut cmds: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut mats: ResMut<Assets<StandardMaterial>>,
+) {
+	let coin_mat = |color| StandardMaterial {
+		base_color: color,
+		metallic: 1.0,
+		perceptual_roughness: 0.3,
+		..default()
+	};
+	cmds.insert_resource(SpecialCoinAssets {
+		// Same size as the coin's collider
+		mesh: meshes.add(Cylinder::new(1.0, 0.25)),
+		golden: mats.add(coin_mat(Color::linear_rgb(1.0, 0.7, 0.1))),
+		multiplier: mats.add(coin_mat(Color::linear_rgb(0.7, 0.1, 1.0))),
+	});
+}
+
+pub fn pick_special_coins(
+	mut cmds: Commands,
+	dropped: Query<Entity, (Added<Coin>, With<CoinDropReason>)>,
+	config: Res<MachineConfig>,
+	assets: Res<SpecialCoinAssets>,
+	mut rng: ResMut<SpecialCoinRng>,
+) {
+	for id in &dropped {
+		let Some(kind) = SpecialCoinConfig::roll(&config.special_coins, &mut rng.0) else {
+			continue;
+		};
+		info!(?id, ?kind, "Special coin");
+		let mat = match kind {
+			SpecialKind::Golden { .. } => assets.golden.clone(),
+			SpecialKind::Multiplier { .. } => assets.multiplier.clone(),
+		};
+		// Swaps the coin's scene for a plain mesh, so it can have its own material
+		cmds.entity(id).remove::<SceneRoot>().insert((
+			kind,
+			Mesh3d(assets.mesh.clone()),
+			MeshMaterial3d(mat),
+		));
+	}
+}
+
+pub fn tick_boost(mut boost: ResMut<CollectionBoost>, t: Res<Time>) {
+	if boost.is_active() {
+		boost.secs = (boost.secs - t.delta_secs()).max(0.0);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bigger_boost_replaces_a_smaller_one() {
+		let mut boost = CollectionBoost::default();
+		boost.start(2.0, 5.0);
+		boost.start(3.0, 1.0);
+		assert_eq!((boost.factor, boost.secs), (3.0, 1.0));
+	}
+
+	#[test]
+	fn smaller_boost_doesnt_cut_a_bigger_one_short() {
+		let mut boost = CollectionBoost::default();
+		boost.start(3.0, 5.0);
+		boost.start(2.0, 10.0);
+		assert_eq!((boost.factor, boost.secs), (3.0, 5.0));
+	}
+
+	#[test]
+	fn same_boost_keeps_the_longer_time() {
+		let mut boost = CollectionBoost::default();
+		boost.start(2.0, 5.0);
+		boost.start(2.0, 3.0);
+		assert_eq!(boost.secs, 5.0);
+		boost.start(2.0, 8.0);
+		assert_eq!(boost.secs, 8.0);
+	}
+
+	#[test]
+	fn any_boost_starts_once_the_last_ran_out() {
+		let mut boost = CollectionBoost::default();
+		boost.start(3.0, 5.0);
+		boost.secs = 0.0;
+		boost.start(2.0, 4.0);
+		assert_eq!((boost.factor, boost.secs), (2.0, 4.0));
+	}
+
+	#[test]
+	fn roll_respects_the_odds() {
+		let configs = [SpecialCoinConfig {
+			kind: SpecialKind::Golden { factor: 10 },
+			odds: 1.0,
+		}];
+		let mut rng = StdRng::seed_from_u64(0);
+		assert_eq!(
+			SpecialCoinConfig::roll(&configs, &mut rng),
+			Some(SpecialKind::Golden { factor: 10 })
+		);
+		assert_eq!(SpecialCoinConfig::roll(&[], &mut rng), None);
+	}
+}
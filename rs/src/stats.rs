@@ -0,0 +1,172 @@
+use crate::coins::{Coin, CoinDropReason};
+use crate::combos::ComboEnded;
+use crate::jackpot::JackpotWon;
+use crate::machine::CoinCollected;
+use crate::pockets::{PocketHit, Reward};
+use crate::power_ups::PowerUpBought;
+use crate::prizes::PrizeCollected;
+use crate::reels::{ReelPayout, ReelResult};
+use bevy::prelude::*;
+use currency::Currency;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<PayoutStats>().add_systems(
+			Update,
+			(
+				count_drops,
+				count_collections,
+				count_pocket_hits,
+				count_jackpots,
+				count_reel_results,
+				count_prizes,
+				count_combos,
+				count_power_ups,
+			),
+		);
+	}
+}
+
+/// Totals for coins the player put in versus what came back out.
+///
+/// Coins that were never dropped (e.g. pre-filled ones) don't count until they move.
+#[derive(Resource, Debug, Clone)]
+pub struct PayoutStats {
+	pub coins_dropped: u64,
+	pub coins_collected: u64,
+	pub value_dropped: Currency,
+	pub value_collected: Currency,
+	/// Hits on each [`crate::pockets::BonusPocket`], by index.
+	pub pocket_hits: Vec<u64>,
+	/// Paid out by pockets, jackpots and prizes directly, rather than by collecting coins.
+	pub value_bonus: Currency,
+	pub jackpots_won: u64,
+	/// Free coins from bonuses, which aren't counted as dropped.
+	pub bonus_coins: u64,
+	pub reel_spins: u64,
+	pub reel_wins: u64,
+	pub prizes_won: u64,
+	/// Golden and multiplier coins collected, see [`crate::special::SpecialKind`].
+	pub special_collected: u64,
+	/// How many combos ended at each size, see [`crate::combos::Combo`].
+	pub combo_sizes: Vec<u64>,
+	pub power_ups_bought: u64,
+	/// Taken out of winnings to buy power-ups.
+	pub value_spent: Currency,
+}
+
+impl PayoutStats {
+	pub fn best_combo(&self) -> usize {
+		self.combo_sizes
+			.iter()
+			.rposition(|&count| count > 0)
+			.unwrap_or(0)
+	}
+}
+
+impl Default for PayoutStats {
+	fn default() -> Self {
+		Self {
+			coins_dropped: 0,
+			coins_collected: 0,
+			value_dropped: Currency::from_str("$0.00").unwrap(),
+			value_collected: Currency::from_str("$0.00").unwrap(),
+			pocket_hits: Vec::new(),
+			value_bonus: Currency::from_str("$0.00").unwrap(),
+			jackpots_won: 0,
+			bonus_coins: 0,
+			reel_spins: 0,
+			reel_wins: 0,
+			prizes_won: 0,
+			special_collected: 0,
+			combo_sizes: Vec::new(),
+			power_ups_bought: 0,
+			value_spent: Currency::from_str("$0.00").unwrap(),
+		}
+	}
+}
+
+pub fn count_drops(
+	mut stats: ResMut<PayoutStats>,
+	dropped: Query<(&Coin, &CoinDropReason), Added<Coin>>,
+) {
+	for (coin, reason) in &dropped {
+		if *reason == CoinDropReason::Bonus {
+			stats.bonus_coins += 1;
+			continue;
+		}
+		stats.coins_dropped += 1;
+		stats.value_dropped = stats.value_dropped.clone() + coin.value.clone();
+	}
+}
+
+pub fn count_collections(mut stats: ResMut<PayoutStats>, mut events: EventReader<CoinCollected>) {
+	for ev in events.read() {
+		if ev.prefilled {
+			continue;
+		}
+		stats.coins_collected += 1;
+		stats.value_collected = stats.value_collected.clone() + ev.paid.clone();
+		if ev.special.is_some() {
+			stats.special_collected += 1;
+		}
+	}
+}
+
+pub fn count_pocket_hits(mut stats: ResMut<PayoutStats>, mut events: EventReader<PocketHit>) {
+	for ev in events.read() {
+		if stats.pocket_hits.len() <= ev.pocket {
+			stats.pocket_hits.resize(ev.pocket + 1, 0);
+		}
+		stats.pocket_hits[ev.pocket] += 1;
+		if let Reward::Credit(value) = &ev.reward {
+			stats.value_bonus = stats.value_bonus.clone() + value.clone();
+		}
+	}
+}
+
+pub fn count_jackpots(mut stats: ResMut<PayoutStats>, mut events: EventReader<JackpotWon>) {
+	for ev in events.read() {
+		stats.jackpots_won += 1;
+		stats.value_bonus = stats.value_bonus.clone() + ev.amount.clone();
+	}
+}
+
+pub fn count_reel_results(mut stats: ResMut<PayoutStats>, mut events: EventReader<ReelResult>) {
+	for ev in events.read() {
+		stats.reel_spins += 1;
+		let Some(payout) = &ev.payout else {
+			continue;
+		};
+		stats.reel_wins += 1;
+		if let ReelPayout::Credit(value) = payout {
+			stats.value_bonus = stats.value_bonus.clone() + value.clone();
+		}
+	}
+}
+
+pub fn count_prizes(mut stats: ResMut<PayoutStats>, mut events: EventReader<PrizeCollected>) {
+	for ev in events.read() {
+		stats.prizes_won += 1;
+		stats.value_bonus = stats.value_bonus.clone() + ev.value.clone();
+	}
+}
+
+pub fn count_combos(mut stats: ResMut<PayoutStats>, mut events: EventReader<ComboEnded>) {
+	for ev in events.read() {
+		let size = ev.size as usize;
+		if stats.combo_sizes.len() <= size {
+			stats.combo_sizes.resize(size + 1, 0);
+		}
+		stats.combo_sizes[size] += 1;
+	}
+}
+
+pub fn count_power_ups(mut stats: ResMut<PayoutStats>, mut events: EventReader<PowerUpBought>) {
+	for ev in events.read() {
+		stats.power_ups_bought += 1;
+		stats.value_spent = stats.value_spent.clone() + ev.cost.clone();
+	}
+}
@@ -0,0 +1,236 @@
+use crate::coins::{drop_coins, AutoDrop, Coin, CoinDropReason, CoinQueue, DropCoin};
+use crate::machine::{InTray, MachineConfig, Piston};
+use crate::{GameState, Winnings};
+use bevy::prelude::*;
+use currency::Currency;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub struct StrategyPlugin;
+
+impl Plugin for StrategyPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<ActiveStrategy>().add_systems(
+			FixedUpdate,
+			run_strategy
+				.before(drop_coins)
+				.run_if(in_state(GameState::Playing)),
+		);
+	}
+}
+
+/// Coins closer to the front of the bed than this are reported in [`Snapshot::edge_coins`].
+pub const EDGE_Y: f32 = -14.0;
+
+/// Automated player. Sees the machine through a [`Snapshot`] every fixed tick and
+/// decides whether to drop coins.
+pub trait Strategy: Send + Sync + 'static {
+	fn name(&self) -> &str;
+	fn decide(&mut self, snapshot: &Snapshot) -> Vec<Decision>;
+}
+
+/// Read-only view of the machine given to a [`Strategy`].
+#[derive(Debug)]
+pub struct Snapshot<'a> {
+	/// Seconds since play started.
+	pub elapsed: f32,
+	/// Seconds since the last snapshot.
+	pub dt: f32,
+	/// Positions of coins on the bed that are close to falling off the front.
+	pub edge_coins: &'a [Vec3],
+	/// See [`Piston::phase`].
+	pub piston_phase: f32,
+	pub balance: &'a Currency,
+	/// Drops waiting in every chute's queue.
+	pub queued: usize,
+	/// Horizontal center of each chute, by index.
+	pub chutes: &'a [f32],
+}
+
+/// A coin the strategy wants to drop. Sent as a manual [`DropCoin`], the same as a click.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Decision {
+	/// See [`DropCoin::chute`].
+	pub chute: Option<usize>,
+	/// See [`DropCoin::aim`].
+	pub aim: Option<f32>,
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ActiveStrategy(pub Option<Box<dyn Strategy>>);
+
+/// Names accepted by [`by_name`], in the order the player cycles through them.
+pub const STRATEGIES: &[&str] = &["random", "timed", "edge-greedy"];
+
+pub fn by_name(name: &str, seed: u64) -> Option<Box<dyn Strategy>> {
+	Some(match name {
+		"random" => Box::new(RandomStrategy::new(0.5, seed)),
+		"timed" => Box::new(TimedStrategy::new(2.0)),
+		"edge-greedy" => Box::new(EdgeGreedyStrategy::new(6, 1.0)),
+		_ => return None,
+	})
+}
+
+pub fn run_strategy(
+	mut strategy: ResMut<ActiveStrategy>,
+	mut auto: ResMut<AutoDrop>,
+	mut events: EventWriter<DropCoin>,
+	coins: Query<&GlobalTransform, (With<Coin>, Without<InTray>)>,
+	pistons: Query<&Piston>,
+	queues: Query<&CoinQueue>,
+	winnings: Res<Winnings>,
+	config: Res<MachineConfig>,
+	t: Res<Time>,
+) {
+	let Some(strategy) = strategy.0.as_mut() else {
+		return;
+	};
+	if **auto {
+		// Strategies replace auto-drop rather than running alongside it
+		**auto = false;
+	}
+
+	let edge_coins = coins
+		.iter()
+		.map(GlobalTransform::translation)
+		// Below the floor means it's already falling
+		.filter(|pos| pos.y < EDGE_Y && pos.z > 0.0)
+		.collect::<Vec<_>>();
+	let chutes = config
+		.chutes
+		.iter()
+		.map(|chute| chute.x)
+		.collect::<Vec<_>>();
+	let snapshot = Snapshot {
+		elapsed: t.elapsed_secs(),
+		dt: t.delta_secs(),
+		edge_coins: &edge_coins,
+		piston_phase: pistons.iter().next().map_or(0.0, Piston::phase),
+		balance: &winnings.0,
+		queued: queues.iter().map(|queue| queue.len()).sum(),
+		chutes: &chutes,
+	};
+
+	for Decision { chute, aim } in strategy.decide(&snapshot) {
+		let mut ev = DropCoin::new(config.coin_value.clone(), CoinDropReason::Manual);
+		ev.chute = chute;
+		ev.aim = aim;
+		events.send(ev);
+	}
+}
+
+/// Drops into a random spot at random times, `rate` coins per second on average.
+pub struct RandomStrategy {
+	pub rate: f32,
+	rng: StdRng,
+}
+
+impl RandomStrategy {
+	pub fn new(rate: f32, seed: u64) -> Self {
+		Self {
+			rate,
+			rng: StdRng::seed_from_u64(seed),
+		}
+	}
+}
+
+impl Strategy for RandomStrategy {
+	fn name(&self) -> &str {
+		"random"
+	}
+
+	fn decide(&mut self, snapshot: &Snapshot) -> Vec<Decision> {
+		if snapshot.queued > 0
+			|| snapshot.chutes.is_empty()
+			|| self.rng.gen::<f32>() >= self.rate * snapshot.dt
+		{
+			return vec![];
+		}
+		vec![Decision {
+			chute: Some(self.rng.gen_range(0..snapshot.chutes.len())),
+			aim: Some(self.rng.gen_range(-1.0..=1.0)),
+		}]
+	}
+}
+
+/// Drops into the active chute every `interval` seconds.
+pub struct TimedStrategy {
+	pub interval: f32,
+	last: Option<f32>,
+}
+
+impl TimedStrategy {
+	pub fn new(interval: f32) -> Self {
+		Self {
+			interval,
+			last: None,
+		}
+	}
+}
+
+impl Strategy for TimedStrategy {
+	fn name(&self) -> &str {
+		"timed"
+	}
+
+	fn decide(&mut self, snapshot: &Snapshot) -> Vec<Decision> {
+		if self
+			.last
+			.is_some_and(|last| snapshot.elapsed - last < self.interval)
+		{
+			return vec![];
+		}
+		self.last = Some(snapshot.elapsed);
+		vec![Decision::default()]
+	}
+}
+
+/// Only drops when at least `min_edge_coins` are close to falling, right as the piston
+/// starts to retract, into whichever chute is closest to the middle of those coins.
+pub struct EdgeGreedyStrategy {
+	pub min_edge_coins: usize,
+	/// Minimum seconds between drops.
+	pub cooldown: f32,
+	last: Option<f32>,
+}
+
+impl EdgeGreedyStrategy {
+	pub fn new(min_edge_coins: usize, cooldown: f32) -> Self {
+		Self {
+			min_edge_coins,
+			cooldown,
+			last: None,
+		}
+	}
+}
+
+impl Strategy for EdgeGreedyStrategy {
+	fn name(&self) -> &str {
+		"edge-greedy"
+	}
+
+	fn decide(&mut self, snapshot: &Snapshot) -> Vec<Decision> {
+		let cooling_down = self
+			.last
+			.is_some_and(|last| snapshot.elapsed - last < self.cooldown);
+		// The first half of the cycle is the push stroke
+		let retracting = (0.5..0.6).contains(&snapshot.piston_phase);
+		if cooling_down
+			|| !retracting
+			|| snapshot.queued > 0
+			|| snapshot.edge_coins.len() < self.min_edge_coins
+		{
+			return vec![];
+		}
+		let target = snapshot.edge_coins.iter().map(|pos| pos.x).sum::<f32>()
+			/ snapshot.edge_coins.len() as f32;
+		let chute = snapshot
+			.chutes
+			.iter()
+			.enumerate()
+			.min_by(|(_, a), (_, b)| (*a - target).abs().total_cmp(&(*b - target).abs()))
+			.map(|(i, _)| i);
+		self.last = Some(snapshot.elapsed);
+		vec![Decision { chute, aim: None }]
+	}
+}
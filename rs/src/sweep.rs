@@ -0,0 +1,189 @@
+use crate::add_simulation;
+use crate::cli::Options;
+use crate::headless::{fixed_step_time, headless_plugins, simulate};
+use crate::machine::MachineConfig;
+use avian3d::math::Vector;
+use avian3d::prelude::{Gravity, SubstepCount};
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+
+/// Machine parameters that can be swept.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Param {
+	PistonSpeed,
+	PistonStroke,
+	FloorFriction,
+	PlatformFriction,
+	Restitution,
+	Gravity,
+	Substeps,
+}
+
+impl Param {
+	pub const ALL: &'static [Self] = &[
+		Self::PistonSpeed,
+		Self::PistonStroke,
+		Self::FloorFriction,
+		Self::PlatformFriction,
+		Self::Restitution,
+		Self::Gravity,
+		Self::Substeps,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::PistonSpeed => "piston-speed",
+			Self::PistonStroke => "piston-stroke",
+			Self::FloorFriction => "floor-friction",
+			Self::PlatformFriction => "platform-friction",
+			Self::Restitution => "restitution",
+			Self::Gravity => "gravity",
+			Self::Substeps => "substeps",
+		}
+	}
+
+	pub fn apply(self, value: f32, app: &mut App) {
+		let world = app.world_mut();
+		match self {
+			Self::PistonSpeed => world.resource_mut::<MachineConfig>().piston_speed = value,
+			Self::PistonStroke => {
+				world.resource_mut::<MachineConfig>().pusher =
+					MachineConfig::pusher_with_stroke(value)
+			}
+			Self::FloorFriction => world.resource_mut::<MachineConfig>().floor_friction = value,
+			Self::PlatformFriction => {
+				world.resource_mut::<MachineConfig>().platform_friction = value
+			}
+			Self::Restitution => world.resource_mut::<MachineConfig>().restitution = value,
+			Self::Gravity => world.insert_resource(Gravity(Vector::NEG_Z * value)),
+			Self::Substeps => world.insert_resource(SubstepCount(value.round().max(1.0) as u32)),
+		}
+	}
+}
+
+/// `<param>=<min>:<max>:<steps>`, e.g. `gravity=10:30:5`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SweepRange {
+	pub param: Param,
+	pub min: f32,
+	pub max: f32,
+	pub steps: usize,
+}
+
+impl SweepRange {
+	/// `steps` evenly spaced values from `min` to `max`, inclusive.
+	pub fn values(&self) -> Vec<f32> {
+		if self.steps <= 1 {
+			return vec![self.min];
+		}
+		(0..self.steps)
+			.map(|i| self.min + (self.max - self.min) * i as f32 / (self.steps - 1) as f32)
+			.collect()
+	}
+}
+
+impl FromStr for SweepRange {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let err = || format!("Expected `<param>=<min>:<max>:<steps>`, got `{s}`");
+		let (name, range) = s.split_once('=').ok_or_else(err)?;
+		let param = Param::ALL
+			.iter()
+			.copied()
+			.find(|param| param.name() == name)
+			.ok_or_else(|| {
+				let names = Param::ALL.iter().map(|p| p.name()).collect::<Vec<_>>();
+				format!("Unknown parameter `{name}`, expected one of {names:?}")
+			})?;
+		let mut parts = range.split(':');
+		let (Some(min), Some(max), Some(steps), None) =
+			(parts.next(), parts.next(), parts.next(), parts.next())
+		else {
+			return Err(err());
+		};
+		Ok(Self {
+			param,
+			min: min.parse().map_err(|_| err())?,
+			max: max.parse().map_err(|_| err())?,
+			steps: steps.parse().map_err(|_| err())?,
+		})
+	}
+}
+
+/// Simulates every combination of `options.sweep` for `options.duration` each, writing
+/// one CSV row per combination to `options.csv`, or stdout.
+pub fn run(options: &Options) -> io::Result<()> {
+	let mut out: Box<dyn Write> = match &options.csv {
+		Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+		None => Box::new(io::stdout().lock()),
+	};
+	let mut options = options.clone();
+	// Something has to drop coins
+	options.strategy.get_or_insert_with(|| "timed".into());
+	// Every configuration should see the same sequence of decisions
+	options.seed.get_or_insert(0);
+
+	let header = options
+		.sweep
+		.iter()
+		.map(|range| range.param.name())
+		.chain([
+			"payout_ratio",
+			"coins_dropped",
+			"coins_collected",
+			"coins_on_bed",
+			"simulated_secs",
+			"cost_secs",
+		])
+		.collect::<Vec<_>>();
+	writeln!(out, "{}", header.join(","))?;
+
+	let combos = options.sweep.iter().fold(vec![vec![]], |combos, range| {
+		combos
+			.into_iter()
+			.flat_map(|combo: Vec<f32>| {
+				range.values().into_iter().map(move |value| {
+					let mut combo = combo.clone();
+					combo.push(value);
+					combo
+				})
+			})
+			.collect()
+	});
+	let total = combos.len();
+	for (i, combo) in combos.into_iter().enumerate() {
+		eprintln!("Configuration {}/{total}: {combo:?}", i + 1);
+		let mut plugins = headless_plugins().build();
+		if i > 0 {
+			// Logging can only be set up once per process
+			plugins = plugins.disable::<LogPlugin>();
+		}
+		let mut app = App::new();
+		app.add_plugins(plugins).insert_resource(fixed_step_time());
+		add_simulation(&mut app, &options);
+		for (range, &value) in options.sweep.iter().zip(&combo) {
+			range.param.apply(value, &mut app);
+		}
+		let report = simulate(&mut app, options.duration);
+
+		let row = combo
+			.iter()
+			.map(f32::to_string)
+			.chain([
+				format!("{:.4}", report.payout_ratio()),
+				report.stats.coins_dropped.to_string(),
+				report.stats.coins_collected.to_string(),
+				report.coins_on_bed.to_string(),
+				format!("{:.3}", report.simulated.as_secs_f64()),
+				format!("{:.3}", report.cost.as_secs_f64()),
+			])
+			.collect::<Vec<_>>();
+		writeln!(out, "{}", row.join(","))?;
+		out.flush()?;
+	}
+	Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::lanes::LaneHistogram;
 use avian3d::debug_render::PhysicsGizmos;
 use avian3d::prelude::PhysicsDebugPlugin;
 use bevy::color::palettes::basic::YELLOW;
@@ -8,8 +9,14 @@ pub struct ToolsPlugin;
 
 impl Plugin for ToolsPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_plugins(PhysicsDebugPlugin::default())
-			.add_systems(Update, (debug_toggles, update_fps.never_param_warn()));
+		app.add_plugins(PhysicsDebugPlugin::default()).add_systems(
+			Update,
+			(
+				debug_toggles,
+				update_fps.never_param_warn(),
+				update_lane_panel.never_param_warn(),
+			),
+		);
 	}
 
 	fn finish(&self, app: &mut App) {
@@ -24,6 +31,7 @@ impl Plugin for ToolsPlugin {
 pub fn debug_toggles(
 	mut cmds: Commands,
 	fps_text: Option<Single<Entity, With<FpsText>>>,
+	lane_panel: Option<Single<Entity, With<LanePanel>>>,
 	keys: Res<ButtonInput<KeyCode>>,
 	mut gizmos: ResMut<GizmoConfigStore>,
 ) {
@@ -43,6 +51,27 @@ pub fn debug_toggles(
 		}
 	}
 
+	// Exit lane histogram
+	if keys.just_pressed(KeyCode::F9) {
+		if let Some(&panel) = lane_panel.as_deref() {
+			cmds.entity(panel).despawn_recursive();
+		} else {
+			cmds.spawn((
+				LanePanel,
+				Text::default(),
+				TextFont::from_font_size(14.0),
+				TextColor(YELLOW.into()),
+				BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.7)),
+				Node {
+					position_type: PositionType::Absolute,
+					bottom: Val::Px(20.0),
+					left: Val::Px(20.0),
+					..default()
+				},
+			));
+		}
+	}
+
 	if keys.just_pressed(KeyCode::Backquote) {
 		let (cfg, _) = gizmos.config_mut::<PhysicsGizmos>();
 		cfg.enabled = !cfg.enabled;
@@ -59,3 +88,15 @@ pub fn update_fps(mut fps_text: Single<&mut Text, With<FpsText>>, diags: Res<Dia
 	let Some(fps) = fps.smoothed() else { return };
 	fps_text.0 = format!("FPS: {fps:.2}");
 }
+
+#[derive(Component)]
+pub struct LanePanel;
+
+pub fn update_lane_panel(
+	mut panel: Single<&mut Text, With<LanePanel>>,
+	histogram: Res<LaneHistogram>,
+) {
+	if histogram.is_changed() || panel.0.is_empty() {
+		panel.0 = format!("Exit lanes: {} coins\n{}", histogram.total(), *histogram);
+	}
+}
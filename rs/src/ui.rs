@@ -1,6 +1,26 @@
+use crate::auto_drop::{AutoDropProgram, AutoDropRun, DropPattern};
 use crate::cam::{CamSwivel, CamTilter};
-use crate::coins::{AutoDrop, AutoDropTimer, CoinCount, DropCoin};
-use crate::Winnings;
+use crate::coins::{
+	ActiveChute, AutoDrop, AutoDropChutes, AutoDropTimer, CancelDrops, CoinCount, CoinDropReason,
+	CoinQueue, DropCoin,
+};
+use crate::combos::Combo;
+use crate::hinges::{Flip, HingeKind};
+use crate::house_edge::HouseEdge;
+use crate::jackpot::Jackpot;
+use crate::machine::{DropZone, EmptyTray, InTray, MachineConfig, PayoutTray};
+use crate::nudge::{Nudge, Tilt};
+use crate::offline::AwaySummary;
+use crate::pockets::BonusPocket;
+use crate::power_ups::{BuyPowerUp, PowerUps};
+use crate::prizes::PrizeInventory;
+use crate::reels::Reels;
+use crate::replay::record_inputs;
+use crate::special::CollectionBoost;
+use crate::stats::PayoutStats;
+use crate::strategy::{self, ActiveStrategy, STRATEGIES};
+use crate::upgrades::{BuyUpgrade, Upgrade, Upgrades};
+use crate::{GameState, Winnings};
 use bevy::color::palettes::basic::{LIME, RED, YELLOW};
 use bevy::color::palettes::css::GOLD;
 use bevy::input::keyboard::KeyboardInput;
@@ -14,7 +34,16 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
 	fn build(&self, app: &mut App) {
 		app.add_systems(Startup, setup_ui)
-			.add_systems(FixedUpdate, drop_coins)
+			.add_systems(
+				Startup,
+				spawn_away_summary.run_if(resource_exists::<AwaySummary>),
+			)
+			.add_systems(
+				FixedUpdate,
+				drop_coins
+					.before(record_inputs)
+					.run_if(in_state(GameState::Playing)),
+			)
 			.add_systems(
 				Update,
 				(
@@ -23,6 +52,35 @@ impl Plugin for UiPlugin {
 					update_auto_text,
 					update_coin_count_text,
 					adjust_auto_timer,
+					select_chute,
+					update_chute_text,
+					cancel_manual_drops,
+					update_queue_text,
+					request_empty_tray,
+					update_tray_text,
+					cycle_auto_program,
+					cycle_strategy,
+					update_house_edge_text,
+					flip_flippers,
+					nudge_cabinet,
+					spawn_pocket_labels,
+					update_pocket_labels,
+				),
+			)
+			.add_systems(
+				Update,
+				(
+					update_jackpot_text,
+					update_reels_text,
+					update_prizes_text,
+					update_boost_text,
+					update_combo_text,
+					update_tilt_text,
+					buy_with_number_keys,
+					toggle_upgrade_screen,
+					update_upgrade_screen.never_param_warn(),
+					dismiss_away_summary,
+					update_power_ups_text,
 				),
 			);
 	}
@@ -35,17 +93,88 @@ pub fn setup_ui(mut cmds: Commands) {
 			bottom: Val::Px(20.0),
 			justify_self: JustifySelf::End,
 			align_self: AlignSelf::End,
+			flex_direction: FlexDirection::Column,
+			align_items: AlignItems::End,
 			..default()
 		},
 	))
-	.with_child((
-		WinningsText,
-		Text("$0.00".into()),
-		TextFont {
-			font_size: 60.0,
+	.with_children(|cmds| {
+		cmds.spawn((
+			WinningsText,
+			Text("$0.00".into()),
+			TextFont {
+				font_size: 60.0,
+				..default()
+			},
+			TextColor(GOLD.into()),
+		));
+		cmds.spawn((
+			BoostText,
+			Text::default(),
+			TextFont::from_font_size(32.0),
+			TextColor(Color::linear_rgb(0.7, 0.1, 1.0)),
+		));
+		cmds.spawn((
+			PrizesText,
+			Text::default(),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+		));
+	});
+
+	cmds.spawn((
+		BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.7)),
+		Node {
+			top: Val::Px(20.0),
+			justify_self: JustifySelf::Center,
+			align_self: AlignSelf::Start,
+			flex_direction: FlexDirection::Column,
+			align_items: AlignItems::Center,
+			..default()
+		},
+	))
+	.with_children(|cmds| {
+		cmds.spawn((
+			JackpotText,
+			Text::default(),
+			TextFont {
+				font_size: 40.0,
+				..default()
+			},
+			TextColor(LIME.into()),
+		));
+		cmds.spawn((
+			TiltText,
+			Text::default(),
+			TextFont::from_font_size(40.0),
+			TextColor(RED.into()),
+		));
+		cmds.spawn((
+			ComboText,
+			Text::default(),
+			TextFont::from_font_size(32.0),
+			TextColor(YELLOW.into()),
+		));
+		cmds.spawn((
+			ReelsText,
+			Text::default(),
+			TextFont::from_font_size(32.0),
+			TextColor(GOLD.into()),
+		));
+	});
+
+	cmds.spawn((
+		PowerUpsText,
+		Text::default(),
+		TextFont::from_font_size(20.0),
+		TextColor::WHITE,
+		BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.7)),
+		Node {
+			position_type: PositionType::Absolute,
+			top: Val::Px(40.0),
+			left: Val::Px(20.0),
 			..default()
 		},
-		TextColor(GOLD.into()),
 	));
 
 	cmds.spawn(Node {
@@ -90,12 +219,85 @@ pub fn setup_ui(mut cmds: Commands) {
 				..default()
 			},
 		));
+		cmds.spawn((
+			Text("P: Change auto program".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+		cmds.spawn((
+			Text("B: Change bot".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+		cmds.spawn((
+			Text("[/]: Select chute".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+		cmds.spawn((
+			Text("C: Cycle chutes on auto".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
 
 		cmds.spawn((
-			AutoText {
-				enabled: false,
-				duration: Duration::from_secs(2),
+			Text("Left/Right Shift: Flippers".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
 			},
+		));
+
+		cmds.spawn((
+			Text("U: Upgrades".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			Text("1-9: Buy power-up".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			Text("Space, Left/Right: Nudge".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			AutoText,
 			Text("Auto: OFF".into()),
 			TextFont::from_font_size(24.0),
 			TextColor(RED.into()),
@@ -105,6 +307,37 @@ pub fn setup_ui(mut cmds: Commands) {
 			},
 		));
 
+		cmds.spawn((
+			Text("Backspace: Cancel queued drops".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			Text("E: Empty payout tray".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			ChuteText,
+			Text("Chute: ".into()),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
 		cmds.spawn((
 			CoinCountText,
 			Text("Coins: 0".into()),
@@ -115,6 +348,39 @@ pub fn setup_ui(mut cmds: Commands) {
 				..default()
 			},
 		));
+
+		cmds.spawn((
+			TrayText,
+			Text::default(),
+			TextFont::from_font_size(24.0),
+			TextColor(YELLOW.into()),
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			HouseEdgeText,
+			Text::default(),
+			TextFont::from_font_size(24.0),
+			TextColor::WHITE,
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
+
+		cmds.spawn((
+			QueueText,
+			Text("Queued: 0 manual, 0 auto".into()),
+			TextFont::from_font_size(24.0),
+			TextColor(YELLOW.into()),
+			Node {
+				align_self: AlignSelf::End,
+				..default()
+			},
+		));
 	});
 }
 
@@ -122,6 +388,8 @@ pub fn drop_coins(
 	mut events: EventWriter<DropCoin>,
 	mut mouse_input: EventReader<MouseButtonInput>,
 	mut auto: ResMut<AutoDrop>,
+	mut strategy: ResMut<ActiveStrategy>,
+	config: Res<MachineConfig>,
 ) {
 	for click in mouse_input.read() {
 		if click.button == MouseButton::Left && click.state == ButtonState::Pressed {
@@ -129,9 +397,19 @@ pub fn drop_coins(
 				info!("Auto: OFF");
 				**auto = false;
 			}
-			events.send(DropCoin::manual("$1.00").unwrap());
+			if strategy.is_some() {
+				info!("Bot: OFF");
+				**strategy = None;
+			}
+			events.send(DropCoin::new(
+				config.coin_value.clone(),
+				CoinDropReason::Manual,
+			));
 		} else if click.button == MouseButton::Right && click.state == ButtonState::Pressed {
 			*auto = !*auto;
+			if **auto {
+				**strategy = None;
+			}
 		}
 	}
 }
@@ -145,6 +423,97 @@ pub fn update_winnings_text(mut q: Single<&mut Text, With<WinningsText>>, winnin
 	}
 }
 
+#[derive(Component, Debug)]
+pub struct PowerUpsText;
+
+/// Lists what's on offer, with the time left on the ones that are running.
+pub fn update_power_ups_text(
+	mut q: Single<&mut Text, With<PowerUpsText>>,
+	power_ups: Res<PowerUps>,
+	config: Res<MachineConfig>,
+) {
+	let mut text = String::new();
+	for (i, offer) in config.power_ups.iter().enumerate() {
+		if i > 0 {
+			text.push('\n');
+		}
+		text += &format!("{}: {} {}", i + 1, offer.kind, offer.cost);
+		if let Some((_, secs)) = power_ups
+			.active
+			.iter()
+			.find(|(kind, _)| *kind == offer.kind)
+		{
+			text += &format!(" [{:.0}s]", secs.ceil());
+		}
+	}
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct TiltText;
+
+pub fn update_tilt_text(
+	mut q: Single<&mut Text, With<TiltText>>,
+	tilt: Res<Tilt>,
+	config: Res<MachineConfig>,
+) {
+	let text = match &config.nudge {
+		Some(_) if tilt.is_locked() => format!("TILT {:.0}s", tilt.locked_for.ceil()),
+		// Warn on the last nudge before tilting
+		Some(nudge) if tilt.nudges_left(nudge) == 0 => "DANGER".into(),
+		_ => String::new(),
+	};
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct ComboText;
+
+pub fn update_combo_text(mut q: Single<&mut Text, With<ComboText>>, combo: Res<Combo>) {
+	// A single coin isn't much of a combo
+	let text = if combo.count > 1 {
+		format!(
+			"COMBO {} (x{:.1}) {:.1}s",
+			combo.count, combo.factor, combo.secs
+		)
+	} else {
+		String::new()
+	};
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct BoostText;
+
+pub fn update_boost_text(mut q: Single<&mut Text, With<BoostText>>, boost: Res<CollectionBoost>) {
+	let text = if boost.is_active() {
+		format!("x{} for {:.1}s", boost.factor, boost.secs)
+	} else {
+		String::new()
+	};
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct PrizesText;
+
+pub fn update_prizes_text(
+	mut q: Single<&mut Text, With<PrizesText>>,
+	inventory: Res<PrizeInventory>,
+) {
+	if inventory.is_changed() {
+		q.0 = format!("Prizes: {}", *inventory);
+	}
+}
+
 pub fn dev_cam(
 	mut swivel: Single<&mut Transform, With<CamSwivel>>,
 	mut tilt: Single<&mut Transform, (With<CamTilter>, Without<CamSwivel>)>,
@@ -161,31 +530,66 @@ pub fn dev_cam(
 }
 
 #[derive(Component, Debug)]
-pub struct AutoText {
-	enabled: bool,
-	duration: Duration,
-}
+pub struct AutoText;
 
 pub fn update_auto_text(
-	mut q: Single<(&mut Text, &mut TextColor, &mut AutoText)>,
+	mut q: Single<(&mut Text, &mut TextColor), With<AutoText>>,
 	auto_enabled: Res<AutoDrop>,
 	auto_timer: Res<AutoDropTimer>,
+	program: Res<AutoDropProgram>,
+	run: Res<AutoDropRun>,
+	strategy: Res<ActiveStrategy>,
 ) {
-	if **auto_enabled == q.2.enabled && auto_timer.duration() == q.2.duration {
+	// The timer changes every tick, so compare against the current text instead.
+	let (text, color) = if let Some(strategy) = &**strategy {
+		(format!("Bot: {}", strategy.name()), LIME)
+	} else if **auto_enabled {
+		let text = match program.pattern {
+			DropPattern::Interval | DropPattern::Burst { .. } => {
+				format!("Auto: {:?} {}", auto_timer.duration(), program.name)
+			}
+			DropPattern::Random { .. } | DropPattern::PistonPhase { .. } => {
+				format!("Auto: {}", program.name)
+			}
+		};
+		(text, LIME)
+	} else if let Some(reason) = run.stopped {
+		(format!("Auto: OFF ({reason:?})"), RED)
+	} else {
+		("Auto: OFF".into(), RED)
+	};
+	if q.0 .0 != text {
+		q.0 .0 = text;
+		q.1 .0 = color.into();
+	}
+}
+
+pub fn cycle_strategy(keys: Res<ButtonInput<KeyCode>>, mut active: ResMut<ActiveStrategy>) {
+	if !keys.just_pressed(KeyCode::KeyB) {
 		return;
 	}
+	let curr = active
+		.0
+		.as_ref()
+		.and_then(|s| STRATEGIES.iter().position(|name| *name == s.name()));
+	let next = match curr {
+		None => Some(0),
+		Some(i) if i + 1 < STRATEGIES.len() => Some(i + 1),
+		Some(_) => None,
+	};
+	**active = next.and_then(|i| strategy::by_name(STRATEGIES[i], rand::random()));
+	info!(bot = ?next.map(|i| STRATEGIES[i]), "Bot");
+}
 
-	if **auto_enabled {
-		q.2.enabled = true;
-		let duration = auto_timer.duration();
-		q.2.duration = duration;
-		q.0 .0 = format!("Auto: {duration:?}");
-		q.1 .0 = LIME.into();
-	} else {
-		q.2.enabled = false;
-		q.0 .0 = "Auto: OFF".into();
-		q.1 .0 = RED.into();
+pub fn cycle_auto_program(keys: Res<ButtonInput<KeyCode>>, mut program: ResMut<AutoDropProgram>) {
+	if !keys.just_pressed(KeyCode::KeyP) {
+		return;
 	}
+	let presets = AutoDropProgram::presets(&program.stop);
+	let curr = presets.iter().position(|p| p.name == program.name);
+	let next = curr.map_or(0, |i| (i + 1) % presets.len());
+	*program = presets[next].clone();
+	info!(program = program.name, "Auto program");
 }
 
 #[derive(Component, Debug)]
@@ -200,26 +604,319 @@ pub fn update_coin_count_text(
 	}
 }
 
-pub fn adjust_auto_timer(mut events: EventReader<KeyboardInput>, mut timer: ResMut<AutoDropTimer>) {
+pub fn adjust_auto_timer(
+	mut events: EventReader<KeyboardInput>,
+	mut timer: ResMut<AutoDropTimer>,
+	config: Res<MachineConfig>,
+) {
+	// The fastest ones are unlocked by upgrades
+	let values = TIMER_VALUES
+		.iter()
+		.copied()
+		.filter(|&value| value >= config.min_auto_interval)
+		.collect::<Vec<_>>();
 	for ev in events.read() {
 		if ev.state == ButtonState::Pressed {
+			// The current one might not be in the list, e.g. after a random auto-drop
 			let curr = timer.duration();
-			let curr_idx = TIMER_VALUES.binary_search(&curr).unwrap_or(2);
 			let new = match ev.key_code {
 				KeyCode::Equal | KeyCode::NumpadAdd => {
-					TIMER_VALUES[(curr_idx + 1).min(TIMER_VALUES.len() - 1)]
-				}
-				KeyCode::Minus | KeyCode::NumpadSubtract => {
-					TIMER_VALUES[curr_idx.saturating_sub(1)]
+					values.iter().find(|&&value| value > curr).or(values.last())
 				}
+				KeyCode::Minus | KeyCode::NumpadSubtract => values
+					.iter()
+					.rev()
+					.find(|&&value| value < curr)
+					.or(values.first()),
 				_ => continue,
 			};
-			timer.set_duration(new);
+			if let Some(&new) = new {
+				timer.set_duration(new);
+			}
+		}
+	}
+}
+
+pub fn select_chute(
+	keys: Res<ButtonInput<KeyCode>>,
+	mut active: ResMut<ActiveChute>,
+	mut auto_chutes: ResMut<AutoDropChutes>,
+	drop_zones: Query<&DropZone>,
+) {
+	let count = drop_zones.iter().count();
+	if count == 0 {
+		return;
+	}
+	if keys.just_pressed(KeyCode::BracketRight) {
+		**active = (**active + 1) % count;
+	}
+	if keys.just_pressed(KeyCode::BracketLeft) {
+		**active = (**active + count - 1) % count;
+	}
+	if keys.just_pressed(KeyCode::KeyC) {
+		*auto_chutes = match *auto_chutes {
+			AutoDropChutes::Active => AutoDropChutes::Cycle,
+			AutoDropChutes::Cycle => AutoDropChutes::Active,
+		};
+		info!(?auto_chutes, "Auto-drop chutes");
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct ChuteText;
+
+pub fn update_chute_text(
+	mut q: Single<&mut Text, With<ChuteText>>,
+	active: Res<ActiveChute>,
+	auto_chutes: Res<AutoDropChutes>,
+	drop_zones: Query<(&DropZone, &Name)>,
+) {
+	if !active.is_changed() && !auto_chutes.is_changed() {
+		return;
+	}
+	let name = drop_zones
+		.iter()
+		.find(|(dz, _)| dz.0 == **active)
+		.map_or("?", |(_, name)| name.as_str());
+	q.0 = match *auto_chutes {
+		AutoDropChutes::Active => format!("Chute: {name}"),
+		AutoDropChutes::Cycle => format!("Chute: {name} (auto cycles)"),
+	};
+}
+
+pub fn cancel_manual_drops(keys: Res<ButtonInput<KeyCode>>, mut events: EventWriter<CancelDrops>) {
+	if keys.just_pressed(KeyCode::Backspace) {
+		events.send(CancelDrops(CoinDropReason::Manual));
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct QueueText;
+
+pub fn update_queue_text(mut q: Single<&mut Text, With<QueueText>>, queues: Query<Ref<CoinQueue>>) {
+	if !queues.iter().any(|queue| queue.is_changed()) {
+		return;
+	}
+	let (mut manual, mut auto, mut bonus) = (0, 0, 0);
+	for ev in queues.iter().flat_map(|queue| queue.into_inner().iter()) {
+		match ev.reason {
+			CoinDropReason::Manual => manual += 1,
+			CoinDropReason::Auto => auto += 1,
+			CoinDropReason::Bonus => bonus += 1,
+		}
+	}
+	q.0 = if bonus > 0 {
+		format!("Queued: {manual} manual, {auto} auto, {bonus} bonus")
+	} else {
+		format!("Queued: {manual} manual, {auto} auto")
+	};
+}
+
+/// Each shift key flips the flippers on its side of the board.
+pub fn flip_flippers(
+	keys: Res<ButtonInput<KeyCode>>,
+	config: Res<MachineConfig>,
+	mut events: EventWriter<Flip>,
+) {
+	let left = keys.just_pressed(KeyCode::ShiftLeft);
+	let right = keys.just_pressed(KeyCode::ShiftRight);
+	if !left && !right {
+		return;
+	}
+	for (i, hinge) in config.hinges.iter().enumerate() {
+		if !matches!(hinge.kind, HingeKind::Flipper { .. }) {
+			continue;
+		}
+		if (left && hinge.pivot.x <= 0.0) || (right && hinge.pivot.x >= 0.0) {
+			events.send(Flip(i));
+		}
+	}
+}
+
+pub fn nudge_cabinet(keys: Res<ButtonInput<KeyCode>>, mut events: EventWriter<Nudge>) {
+	let mut direction = Vec2::ZERO;
+	if keys.just_pressed(KeyCode::Space) {
+		// Toward the player, where coins fall off
+		direction.y -= 1.0;
+	}
+	if keys.just_pressed(KeyCode::ArrowLeft) {
+		direction.x -= 1.0;
+	}
+	if keys.just_pressed(KeyCode::ArrowRight) {
+		direction.x += 1.0;
+	}
+	if direction != Vec2::ZERO {
+		events.send(Nudge(direction.normalize()));
+	}
+}
+
+/// Number keys buy upgrades while the [`UpgradeScreen`] is open, and power-ups otherwise.
+pub fn buy_with_number_keys(
+	keys: Res<ButtonInput<KeyCode>>,
+	screen: Query<(), With<UpgradeScreen>>,
+	mut power_ups: EventWriter<BuyPowerUp>,
+	mut upgrades: EventWriter<BuyUpgrade>,
+) {
+	const DIGITS: [KeyCode; 9] = [
+		KeyCode::Digit1,
+		KeyCode::Digit2,
+		KeyCode::Digit3,
+		KeyCode::Digit4,
+		KeyCode::Digit5,
+		KeyCode::Digit6,
+		KeyCode::Digit7,
+		KeyCode::Digit8,
+		KeyCode::Digit9,
+	];
+	for (i, key) in DIGITS.into_iter().enumerate() {
+		if !keys.just_pressed(key) {
+			continue;
+		}
+		if screen.is_empty() {
+			power_ups.send(BuyPowerUp(i));
+		} else {
+			upgrades.send(BuyUpgrade(i));
 		}
 	}
 }
 
+/// Shows the [`AwaySummary`] until any key or button is pressed.
+#[derive(Component, Debug)]
+pub struct AwayPanel;
+
+pub fn spawn_away_summary(mut cmds: Commands, away: Res<AwaySummary>) {
+	cmds.spawn((
+		AwayPanel,
+		Text(format!("{}\n\nPress any key", *away)),
+		TextFont::from_font_size(32.0),
+		TextColor(GOLD.into()),
+		TextLayout::new_with_justify(JustifyText::Center),
+		BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.9)),
+		Node {
+			position_type: PositionType::Absolute,
+			justify_self: JustifySelf::Center,
+			align_self: AlignSelf::Center,
+			padding: UiRect::all(Val::Px(20.0)),
+			..default()
+		},
+	));
+}
+
+pub fn dismiss_away_summary(
+	mut cmds: Commands,
+	panel: Option<Single<Entity, With<AwayPanel>>>,
+	keys: Res<ButtonInput<KeyCode>>,
+	buttons: Res<ButtonInput<MouseButton>>,
+) {
+	let Some(panel) = panel else {
+		return;
+	};
+	if keys.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some() {
+		cmds.entity(*panel).despawn_recursive();
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct UpgradeScreen;
+
+pub fn toggle_upgrade_screen(
+	mut cmds: Commands,
+	keys: Res<ButtonInput<KeyCode>>,
+	screen: Option<Single<Entity, With<UpgradeScreen>>>,
+) {
+	if !keys.just_pressed(KeyCode::KeyU) {
+		return;
+	}
+	if let Some(&screen) = screen.as_deref() {
+		cmds.entity(screen).despawn_recursive();
+		return;
+	}
+	cmds.spawn((
+		UpgradeScreen,
+		Text::default(),
+		TextFont::from_font_size(28.0),
+		TextColor::WHITE,
+		BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.9)),
+		Node {
+			position_type: PositionType::Absolute,
+			justify_self: JustifySelf::Center,
+			align_self: AlignSelf::Center,
+			padding: UiRect::all(Val::Px(20.0)),
+			..default()
+		},
+	));
+}
+
+pub fn update_upgrade_screen(
+	mut q: Single<&mut Text, With<UpgradeScreen>>,
+	upgrades: Res<Upgrades>,
+	winnings: Res<Winnings>,
+) {
+	let mut text = format!(
+		"UPGRADES\nBalance: {}\nBought upgrades take effect next session.\n",
+		winnings.0
+	);
+	for (i, upgrade) in Upgrade::ALL.iter().enumerate() {
+		let level = upgrades.levels[i];
+		let cost = upgrade
+			.cost(level)
+			.map_or("MAX".into(), |cost| cost.to_string());
+		text += &format!(
+			"\n{}: {upgrade:<18} {level}/{} {cost}",
+			i + 1,
+			upgrade.max_level()
+		);
+	}
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+pub fn request_empty_tray(keys: Res<ButtonInput<KeyCode>>, mut events: EventWriter<EmptyTray>) {
+	if keys.just_pressed(KeyCode::KeyE) {
+		events.send(EmptyTray);
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct TrayText;
+
+pub fn update_tray_text(
+	mut q: Single<&mut Text, With<TrayText>>,
+	tray: Option<Single<&PayoutTray>>,
+	tray_coins: Query<(), With<InTray>>,
+) {
+	let text = match tray {
+		Some(tray) => format!("Tray: {}/{}", tray_coins.iter().len(), tray.capacity),
+		None => String::new(),
+	};
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+/// Discloses that the house edge controller is running.
+#[derive(Component, Debug)]
+pub struct HouseEdgeText;
+
+pub fn update_house_edge_text(
+	mut q: Single<&mut Text, With<HouseEdgeText>>,
+	edge: Option<Res<HouseEdge>>,
+) {
+	let Some(edge) = edge.filter(|edge| edge.is_changed()) else {
+		return;
+	};
+	q.0 = format!(
+		"House edge control: {:.0}% hold target, {} adjustments",
+		edge.target_hold * 100.0,
+		edge.log.len()
+	);
+}
+
 pub const TIMER_VALUES: &[Duration] = &[
+	Duration::from_millis(100),
+	Duration::from_millis(200),
+	Duration::from_millis(300),
 	Duration::from_millis(500),
 	Duration::from_secs(1),
 	Duration::from_secs(2),
@@ -242,3 +939,102 @@ pub const TIMER_VALUES: &[Duration] = &[
 	Duration::from_secs(2700), // 45m
 	Duration::from_secs(3600), // 1h
 ];
+
+/// Follows a [`BonusPocket`] around the screen.
+#[derive(Component, Debug)]
+pub struct PocketLabel(pub Entity);
+
+pub fn spawn_pocket_labels(mut cmds: Commands, pockets: Query<Entity, Added<BonusPocket>>) {
+	for id in &pockets {
+		cmds.spawn((
+			PocketLabel(id),
+			Text::default(),
+			TextFont::from_font_size(16.0),
+			TextColor(GOLD.into()),
+			Node {
+				position_type: PositionType::Absolute,
+				..default()
+			},
+		));
+	}
+}
+
+pub fn update_pocket_labels(
+	mut cmds: Commands,
+	mut labels: Query<(Entity, &PocketLabel, &mut Node, &mut Text)>,
+	pockets: Query<(&BonusPocket, &Name, &GlobalTransform)>,
+	cam: Single<(&Camera, &GlobalTransform)>,
+	stats: Res<PayoutStats>,
+) {
+	let (cam, cam_xform) = *cam;
+	for (id, label, mut node, mut text) in &mut labels {
+		let Ok((pocket, name, xform)) = pockets.get(label.0) else {
+			cmds.entity(id).despawn_recursive();
+			continue;
+		};
+		let Ok(pos) = cam.world_to_viewport(cam_xform, xform.translation()) else {
+			continue;
+		};
+		node.left = Val::Px(pos.x);
+		node.top = Val::Px(pos.y);
+		let hits = stats.pocket_hits.get(pocket.index).copied().unwrap_or(0);
+		let new = format!("{name} ({hits})");
+		if text.0 != new {
+			text.0 = new;
+		}
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct JackpotText;
+
+pub fn update_jackpot_text(
+	mut q: Single<&mut Text, With<JackpotText>>,
+	jackpot: Option<Res<Jackpot>>,
+) {
+	let text = match &jackpot {
+		Some(jackpot) => format!("JACKPOT {}", jackpot.pool()),
+		None => String::new(),
+	};
+	if q.0 != text {
+		q.0 = text;
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct ReelsText;
+
+pub fn update_reels_text(
+	mut q: Single<&mut Text, With<ReelsText>>,
+	reels: Option<Res<Reels>>,
+	t: Res<Time>,
+) {
+	let Some(reels) = &reels else {
+		if !q.0.is_empty() {
+			q.0.clear();
+		}
+		return;
+	};
+	let names = &reels.config.symbols;
+	let showing: Vec<_> = match reels.spinning {
+		// Just for show, the result is rolled when the reels stop
+		Some(_) => (0..reels.showing.len())
+			.map(|reel| {
+				let tick = (t.elapsed_secs() * 12.0) as usize + reel * 3;
+				names[tick % names.len().max(1)].name.as_str()
+			})
+			.collect(),
+		None => reels
+			.showing
+			.iter()
+			.map(|&symbol| names[symbol].name.as_str())
+			.collect(),
+	};
+	let mut text = format!("[ {} ]", showing.join(" | "));
+	if reels.stock > 0 {
+		text += &format!(" Stock: {}", reels.stock);
+	}
+	if q.0 != text {
+		q.0 = text;
+	}
+}
@@ -0,0 +1,305 @@
+use crate::machine::{ChuteConfig, ChuteKind, MachineConfig};
+use crate::{cents, from_cents, Winnings};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use currency::Currency;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Permanent upgrades, bought with [`Winnings`] and kept between sessions. They're applied
+/// to the [`MachineConfig`] at startup, so anything bought during a session takes effect
+/// in the next one.
+pub struct UpgradesPlugin;
+
+impl Plugin for UpgradesPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Upgrades>()
+			.add_event::<BuyUpgrade>()
+			.add_systems(Update, buy_upgrades)
+			.add_systems(Last, save_upgrades);
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Upgrade {
+	/// Lets the auto-drop timer go below half a second.
+	AutoDropInterval,
+	/// Every dropped coin is worth more, and pays out more.
+	CoinValue,
+	/// The piston pushes further forward.
+	PistonStroke,
+	/// Bypass chutes at the edges of the platform.
+	ExtraChutes,
+	/// Better odds for special coins and the jackpot lottery.
+	BonusOdds,
+}
+
+impl Upgrade {
+	pub const ALL: [Self; 5] = [
+		Self::AutoDropInterval,
+		Self::CoinValue,
+		Self::PistonStroke,
+		Self::ExtraChutes,
+		Self::BonusOdds,
+	];
+
+	/// Name in the save file.
+	pub fn key(self) -> &'static str {
+		match self {
+			Self::AutoDropInterval => "auto-drop-interval",
+			Self::CoinValue => "coin-value",
+			Self::PistonStroke => "piston-stroke",
+			Self::ExtraChutes => "extra-chutes",
+			Self::BonusOdds => "bonus-odds",
+		}
+	}
+
+	pub fn max_level(self) -> u32 {
+		match self {
+			Self::AutoDropInterval => 3,
+			Self::CoinValue => 4,
+			Self::PistonStroke => 3,
+			Self::ExtraChutes => 2,
+			Self::BonusOdds => 5,
+		}
+	}
+
+	/// What the next level costs, from `level`, or `None` if it's maxed out.
+	pub fn cost(self, level: u32) -> Option<Currency> {
+		if level >= self.max_level() {
+			return None;
+		}
+		let base = match self {
+			Self::AutoDropInterval => 2000,
+			Self::CoinValue => 5000,
+			Self::PistonStroke => 3000,
+			Self::ExtraChutes => 4000,
+			Self::BonusOdds => 2500,
+		};
+		// Each level costs twice the one before
+		Some(from_cents(base << level))
+	}
+
+	/// Changes `config` to what it is at `level`, starting from the defaults.
+	fn apply(self, level: u32, config: &mut MachineConfig) {
+		if level == 0 {
+			return;
+		}
+		match self {
+			Self::AutoDropInterval => {
+				config.min_auto_interval = [
+					Duration::from_millis(500),
+					Duration::from_millis(300),
+					Duration::from_millis(200),
+					Duration::from_millis(100),
+				][level.min(3) as usize];
+			}
+			Self::CoinValue => {
+				// $1 more per level
+				config.coin_value = from_cents(cents(&config.coin_value) + 100 * level as i64);
+			}
+			Self::PistonStroke => {
+				// 10% further per level, whatever the motion is
+				config.pusher.scale_stroke(1.0 + 0.1 * level as f32);
+			}
+			Self::ExtraChutes => {
+				for (name, x) in [("Left edge", -8.0), ("Right edge", 8.0)]
+					.into_iter()
+					.take(level as usize)
+				{
+					config.chutes.push(ChuteConfig {
+						name: name.into(),
+						x,
+						width: 4.0,
+						kind: ChuteKind::Bypass,
+					});
+				}
+			}
+			Self::BonusOdds => {
+				let factor = 1.0 + 0.25 * level as f64;
+				for special in &mut config.special_coins {
+					special.odds *= factor;
+				}
+				if let Some(jackpot) = &mut config.jackpot {
+					jackpot.lottery_odds *= factor;
+				}
+			}
+		}
+	}
+}
+
+impl fmt::Display for Upgrade {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// Padded, so it lines up in the upgrade screen
+		f.pad(match self {
+			Self::AutoDropInterval => "Faster auto-drop",
+			Self::CoinValue => "Coin value",
+			Self::PistonStroke => "Piston stroke",
+			Self::ExtraChutes => "Extra chutes",
+			Self::BonusOdds => "Bonus odds",
+		})
+	}
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Upgrades {
+	/// Level of each upgrade, in the order of [`Upgrade::ALL`].
+	pub levels: [u32; Upgrade::ALL.len()],
+	/// Where upgrades are saved between sessions.
+	pub file: Option<PathBuf>,
+}
+
+impl Upgrades {
+	/// Picks up the upgrades saved in `file`, if there is one.
+	pub fn load(file: Option<PathBuf>) -> io::Result<Self> {
+		let mut this = match &file {
+			Some(path) if path.exists() => fs::read_to_string(path)?
+				.parse()
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+			_ => Self::default(),
+		};
+		this.file = file;
+		Ok(this)
+	}
+
+	pub fn save(&self) -> io::Result<()> {
+		match &self.file {
+			Some(path) => fs::write(path, self.to_string()),
+			None => Ok(()),
+		}
+	}
+
+	pub fn level(&self, upgrade: Upgrade) -> u32 {
+		self.levels[Upgrade::ALL.iter().position(|&u| u == upgrade).unwrap()]
+	}
+
+	pub fn apply(&self, config: &mut MachineConfig) {
+		for (upgrade, &level) in Upgrade::ALL.iter().zip(&self.levels) {
+			upgrade.apply(level.min(upgrade.max_level()), config);
+		}
+	}
+}
+
+/// One `<key>=<level>` line per upgrade.
+impl fmt::Display for Upgrades {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (upgrade, level) in Upgrade::ALL.iter().zip(&self.levels) {
+			writeln!(f, "{}={level}", upgrade.key())?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for Upgrades {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut this = Self::default();
+		for line in s.lines().filter(|line| !line.trim().is_empty()) {
+			let err = || format!("Expected `<upgrade>=<level>`, got `{line}`");
+			let (key, level) = line.split_once('=').ok_or_else(err)?;
+			let index = Upgrade::ALL
+				.iter()
+				.position(|upgrade| upgrade.key() == key.trim())
+				.ok_or_else(|| format!("Unknown upgrade `{}`", key.trim()))?;
+			this.levels[index] = level.trim().parse().map_err(|_| err())?;
+		}
+		Ok(this)
+	}
+}
+
+/// Buys the next level of the upgrade at this index into [`Upgrade::ALL`], if the player
+/// can afford it.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BuyUpgrade(pub usize);
+
+pub fn buy_upgrades(
+	mut events: EventReader<BuyUpgrade>,
+	mut upgrades: ResMut<Upgrades>,
+	mut winnings: ResMut<Winnings>,
+) {
+	for &BuyUpgrade(index) in events.read() {
+		let Some(&upgrade) = Upgrade::ALL.get(index) else {
+			continue;
+		};
+		let level = upgrades.levels[index];
+		let Some(cost) = upgrade.cost(level) else {
+			info!(%upgrade, "Upgrade is maxed out");
+			continue;
+		};
+		let balance = cents(&winnings.0);
+		if balance < cents(&cost) {
+			info!(%upgrade, %cost, "Can't afford upgrade");
+			continue;
+		}
+		winnings.0 = from_cents(balance - cents(&cost));
+		upgrades.levels[index] += 1;
+		info!(%upgrade, level = level + 1, %cost, "Bought upgrade");
+	}
+}
+
+/// Saves upgrades as soon as they change, and on exit.
+pub fn save_upgrades(upgrades: Res<Upgrades>, mut exit: EventReader<AppExit>) {
+	let exiting = exit.read().count() > 0;
+	// Loading them isn't a change worth saving
+	let changed = upgrades.is_changed() && !upgrades.is_added();
+	if !changed && !exiting {
+		return;
+	}
+	if let Err(e) = upgrades.save() {
+		error!(path = ?upgrades.file, "Failed to save upgrades: {e}");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::pusher::PusherMotion;
+
+	#[test]
+	fn upgrades_round_trip() {
+		let upgrades = Upgrades {
+			levels: [1, 0, 3, 2, 5],
+			file: None,
+		};
+		let parsed = upgrades.to_string().parse::<Upgrades>().unwrap();
+		assert_eq!(parsed.levels, upgrades.levels);
+	}
+
+	#[test]
+	fn unknown_upgrades_are_rejected() {
+		assert!("turbo=1".parse::<Upgrades>().is_err());
+		assert!("coin-value=high".parse::<Upgrades>().is_err());
+	}
+
+	#[test]
+	fn cost_doubles_until_maxed_out() {
+		let upgrade = Upgrade::ExtraChutes;
+		assert_eq!(cents(&upgrade.cost(0).unwrap()), 4000);
+		assert_eq!(cents(&upgrade.cost(1).unwrap()), 8000);
+		assert!(upgrade.cost(2).is_none());
+	}
+
+	#[test]
+	fn piston_stroke_stretches_the_existing_motion() {
+		let mut config = MachineConfig {
+			pusher: PusherMotion::linear(Vec3::ZERO, Vec3::new(0.0, -5.0, 1.0), 3.0, 0.5),
+			..default()
+		};
+		Upgrades {
+			levels: [0, 0, 2, 0, 0],
+			file: None,
+		}
+		.apply(&mut config);
+		let keyframes = &config.pusher.keyframes;
+		assert_eq!(keyframes[0].translation, Vec3::ZERO);
+		assert!(keyframes[1]
+			.translation
+			.abs_diff_eq(Vec3::new(0.0, -6.0, 1.2), 1e-5));
+		assert_eq!(keyframes[1].duration, 3.0);
+	}
+}